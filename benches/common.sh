@@ -0,0 +1,44 @@
+# Shared by the benchmark scripts: builds the shell at a git revision and times commands in it.
+
+# build_shell REV OUT: a release build of the shell at REV, or of the working tree when REV
+# is `.`, copied to OUT
+build_shell() {
+    local rev=$1 out=$2 root tree
+    root=$(git rev-parse --show-toplevel)
+    if [ "$rev" = . ]; then
+        cargo build --release -q --manifest-path "$root/Cargo.toml"
+        cp "$root/target/release/shell" "$out"
+        return
+    fi
+
+    tree=$(mktemp -d)
+    git -C "$root" worktree add -q --detach "$tree" "$rev"
+    # Cargo.lock is not tracked; the working tree's keeps the old build on the same crates
+    if [ -f "$root/Cargo.lock" ]; then
+        cp "$root/Cargo.lock" "$tree/"
+    fi
+    cargo build --release -q --manifest-path "$tree/Cargo.toml"
+    cp "$tree/target/release/shell" "$out"
+    git -C "$root" worktree remove --force "$tree"
+}
+
+# best_of RUNS SHELL DIR COMMAND [SETUP]: the fastest of RUNS runs of COMMAND in DIR, printed
+# in seconds; SETUP is run by bash in DIR before each run. The shell only runs on a terminal,
+# so it is given one with script(1) and its output thrown away.
+best_of() {
+    local runs=$1 shell=$2 dir=$3 command=$4 setup=${5:-:} input best='' start end elapsed
+    input=$(mktemp)
+    printf 'cd %s\n%s\n' "$dir" "$command" > "$input"
+    for _ in $(seq "$runs"); do
+        (cd "$dir" && eval "$setup")
+        start=$(date +%s%N)
+        script -qec "$shell < $input" /dev/null > /dev/null
+        end=$(date +%s%N)
+        elapsed=$(( (end - start) / 1000000 ))
+        if [ -z "$best" ] || [ "$elapsed" -lt "$best" ]; then
+            best=$elapsed
+        fi
+    done
+    rm -f "$input"
+    printf '%d.%03ds' $(( best / 1000 )) $(( best % 1000 ))
+}
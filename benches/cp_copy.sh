@@ -0,0 +1,47 @@
+#!/usr/bin/env bash
+# Times cp on a 1 GiB file of random data and on a 4 GiB sparse file holding 64 MiB of data,
+# with the shell built at BASE_REV and with the working tree.
+#
+# usage: benches/cp_copy.sh BASE_REV [DIR]
+#
+# DIR, a new temporary directory by default, needs about 6 GiB free since BASE_REV may write
+# the sparse file out in full. RUNS (default 5) sets how many runs the best time is taken from.
+set -euo pipefail
+
+base=${1:?usage: benches/cp_copy.sh BASE_REV [DIR]}
+runs=${RUNS:-5}
+here=$(cd "$(dirname "$0")" && pwd)
+. "$here/common.sh"
+
+if [ -n "${2:-}" ]; then
+    dir=$2
+    mkdir -p "$dir"
+else
+    dir=$(mktemp -d)
+    trap 'rm -rf "$dir"' EXIT
+fi
+
+cd "$here"
+echo "building $base and the working tree..."
+build_shell "$base" "$dir/shell-base"
+build_shell . "$dir/shell-new"
+
+echo "writing the test files to $dir..."
+head -c 1G /dev/urandom > "$dir/dense"
+rm -f "$dir/sparse"
+truncate -s 4G "$dir/sparse"
+for i in $(seq 0 63); do
+    dd if=/dev/urandom of="$dir/sparse" bs=1M count=1 seek=$(( i * 64 )) conv=notrunc status=none
+done
+
+label=$(git rev-parse --short "$base")
+printf '%-22s %-12s %s\n' '' "$label" 'working tree'
+for file in dense sparse; do
+    base_time=$(best_of "$runs" "$dir/shell-base" "$dir" "cp $file copy" 'rm -f copy')
+    base_usage=$(du -h "$dir/copy" | cut -f1)
+    new_time=$(best_of "$runs" "$dir/shell-new" "$dir" "cp $file copy" 'rm -f copy')
+    new_usage=$(du -h "$dir/copy" | cut -f1)
+    printf '%-22s %-12s %s\n' "cp $file" "$base_time" "$new_time"
+    printf '%-22s %-12s %s\n' "  copy on disk" "$base_usage" "$new_usage"
+done
+rm -f "$dir/copy"
@@ -0,0 +1,47 @@
+#!/usr/bin/env bash
+# Times ls on a directory of 100k entries (files, symlinks, a few dangling, and directories),
+# with the shell built at BASE_REV and with the working tree.
+#
+# usage: benches/ls_large_dir.sh BASE_REV [DIR]
+#
+# DIR is a new temporary directory by default. RUNS (default 5) sets how many runs the best
+# time is taken from.
+set -euo pipefail
+
+base=${1:?usage: benches/ls_large_dir.sh BASE_REV [DIR]}
+runs=${RUNS:-5}
+here=$(cd "$(dirname "$0")" && pwd)
+. "$here/common.sh"
+
+if [ -n "${2:-}" ]; then
+    dir=$2
+    mkdir -p "$dir"
+else
+    dir=$(mktemp -d)
+    trap 'rm -rf "$dir"' EXIT
+fi
+
+cd "$here"
+echo "building $base and the working tree..."
+build_shell "$base" "$dir/shell-base"
+build_shell . "$dir/shell-new"
+
+echo "creating 100000 entries in $dir/entries..."
+rm -rf "$dir/entries"
+mkdir "$dir/entries"
+(
+    cd "$dir/entries"
+    seq -f 'file%06g' 1 90000 | xargs touch
+    seq -f 'dir%06g' 1 5000 | xargs mkdir
+    # every tenth link dangles
+    seq 1 5000 | awk '{ printf "%s%06d link%06d\n", $1 % 10 ? "file" : "missing", $1, $1 }' |
+        xargs -n 2 ln -s
+)
+
+label=$(git rev-parse --short "$base")
+printf '%-22s %-12s %s\n' '' "$label" 'working tree'
+for command in 'ls' 'ls -F' 'ls -l' 'ls -l --color=always'; do
+    base_time=$(best_of "$runs" "$dir/shell-base" "$dir/entries" "$command")
+    new_time=$(best_of "$runs" "$dir/shell-new" "$dir/entries" "$command")
+    printf '%-22s %-12s %s\n' "$command" "$base_time" "$new_time"
+done
@@ -0,0 +1,218 @@
+use std::{
+    fs::{File, Metadata},
+    io,
+    os::{
+        fd::AsRawFd,
+        unix::fs::{FileExt, MetadataExt},
+    },
+};
+
+const WHEN: [&str; 3] = ["never", "auto", "always"];
+// copy_file_range is asked for at most this much at once, which also paces --progress
+const MAX_CHUNK: u64 = 64 << 20;
+const BUFFER_SIZE: usize = 128 * 1024;
+// the granularity at which --sparse=always looks for zeros to turn into holes
+const BLOCK_SIZE: usize = 4096;
+
+// --reflink: whether the copy may share the source's blocks until one of them is written
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Reflink {
+    Never,
+    #[default]
+    Auto,
+    Always,
+}
+
+// --sparse: which parts of the copy are left as holes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Sparse {
+    Never,
+    // the holes of a source that has any
+    #[default]
+    Auto,
+    // every block of zeros as well
+    Always,
+}
+
+impl Reflink {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "never" => Ok(Reflink::Never),
+            "auto" => Ok(Reflink::Auto),
+            "always" => Ok(Reflink::Always),
+            _ => Err(invalid_argument(value, "reflink")),
+        }
+    }
+}
+
+impl Sparse {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "never" => Ok(Sparse::Never),
+            "auto" => Ok(Sparse::Auto),
+            "always" => Ok(Sparse::Always),
+            _ => Err(invalid_argument(value, "sparse")),
+        }
+    }
+}
+
+fn invalid_argument(value: &str, option: &str) -> String {
+    let valid: Vec<String> = WHEN.iter().map(|when| format!("  - '{}'", when)).collect();
+    format!(
+        "cp: invalid argument '{}' for '--{}'\nValid arguments are:\n{}",
+        value,
+        option,
+        valid.join("\n")
+    )
+}
+
+// shares the source's extents with the copy, which only copy-on-write filesystems can do
+pub fn clone(input: &File, output: &File) -> io::Result<()> {
+    if unsafe { libc::ioctl(output.as_raw_fd(), libc::FICLONE, input.as_raw_fd()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// copies a file's data in the kernel where it can, skipping over the holes of a sparse
+// source; anything that is not a regular file is simply read until it ends. `progress` is
+// told how many bytes each step wrote
+pub fn copy(
+    input: &File,
+    output: &File,
+    meta: &Metadata,
+    sparse: Sparse,
+    progress: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    if !meta.is_file() {
+        progress(io::copy(&mut &*input, &mut &*output)?);
+        return Ok(());
+    }
+
+    // fewer blocks than the size needs means some of it is holes
+    let has_holes = meta.blocks() * 512 < meta.size();
+    match sparse {
+        // the kernel might keep holes, so they are written out as zeros by hand
+        Sparse::Never if has_holes => {
+            return write_extent(input, output, 0, u64::MAX, false, progress);
+        }
+        Sparse::Never | Sparse::Auto if !has_holes => {
+            return copy_extent(input, output, 0, u64::MAX, progress);
+        }
+        _ => {}
+    }
+
+    let size = meta.size();
+    let mut offset = 0;
+    while offset < size {
+        let start = match seek(input, offset, libc::SEEK_DATA) {
+            Ok(start) => start,
+            // nothing but a hole up to the end
+            Err(err) if err.raw_os_error() == Some(libc::ENXIO) => break,
+            // a filesystem that can't say where the data is gets it all copied
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) => offset,
+            Err(err) => return Err(err),
+        };
+        let end = match seek(input, start, libc::SEEK_HOLE) {
+            Ok(end) => end.min(size),
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) => size,
+            Err(err) => return Err(err),
+        };
+
+        if sparse == Sparse::Always {
+            write_extent(input, output, start, end, true, progress)?;
+        } else {
+            copy_extent(input, output, start, end, progress)?;
+        }
+        offset = end;
+    }
+
+    // a hole at the end is only there once the size is
+    output.set_len(size)
+}
+
+fn seek(file: &File, offset: u64, whence: libc::c_int) -> io::Result<u64> {
+    let position = unsafe { libc::lseek(file.as_raw_fd(), offset as libc::off_t, whence) };
+    if position < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(position as u64)
+}
+
+// copies from `start` up to `end` or the end of the file with copy_file_range, falling back
+// to reading and writing where the kernel can't, as across filesystems on older kernels
+fn copy_extent(
+    input: &File,
+    output: &File,
+    start: u64,
+    end: u64,
+    progress: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    let mut offset = start;
+    while offset < end {
+        let len = (end - offset).min(MAX_CHUNK) as usize;
+        let mut offset_in = offset as libc::loff_t;
+        let mut offset_out = offset as libc::loff_t;
+        let copied = unsafe {
+            libc::copy_file_range(
+                input.as_raw_fd(),
+                &mut offset_in,
+                output.as_raw_fd(),
+                &mut offset_out,
+                len,
+                0,
+            )
+        };
+
+        if copied < 0 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::EXDEV | libc::ENOSYS | libc::EINVAL | libc::EOPNOTSUPP) => {
+                    return write_extent(input, output, offset, end, false, progress);
+                }
+                _ => return Err(err),
+            }
+        }
+        if copied == 0 {
+            break;
+        }
+        offset += copied as u64;
+        progress(copied as u64);
+    }
+    Ok(())
+}
+
+// the same through a buffer, leaving out blocks of zeros when `skip_zeros` is set
+fn write_extent(
+    input: &File,
+    output: &File,
+    start: u64,
+    end: u64,
+    skip_zeros: bool,
+    progress: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut offset = start;
+
+    while offset < end {
+        let len = (end - offset).min(BUFFER_SIZE as u64) as usize;
+        let read = match input.read_at(&mut buffer[..len], offset) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        let mut block_offset = offset;
+        for block in buffer[..read].chunks(BLOCK_SIZE) {
+            if !skip_zeros || block.iter().any(|&byte| byte != 0) {
+                output.write_all_at(block, block_offset)?;
+            }
+            block_offset += block.len() as u64;
+        }
+        offset += read as u64;
+        progress(read as u64);
+    }
+    Ok(())
+}
@@ -0,0 +1,688 @@
+mod data;
+mod parallel;
+mod preserve;
+
+use super::{overwrite::Policy, progress::Report};
+use crate::utils::{direct_children, error_message};
+use data::{Reflink, Sparse};
+use parallel::Deferred;
+use preserve::Preserve;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    ffi::CString,
+    fs::{self, DirBuilder, File, Metadata, OpenOptions, Permissions},
+    io,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt, symlink},
+    },
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, Clone)]
+struct Flags {
+    recursive: bool,
+    preserve: Preserve,
+    // unset, symlinks are followed unless copying recursively, like GNU cp
+    dereference: Option<Dereference>,
+    reflink: Reflink,
+    sparse: Sparse,
+    // -j: how many files are copied at once
+    jobs: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dereference {
+    // -P: copy symlinks as symlinks
+    Never,
+    // -H: follow only symlinks named on the command line
+    CommandLine,
+    // -L: follow every symlink
+    Always,
+}
+
+#[derive(Debug)]
+pub struct Cp {
+    // what errors are prefixed with, `mv` when it copies across filesystems
+    cmd: &'static str,
+    flags: Flags,
+    policy: Policy,
+    report: Report,
+    pub sources: Vec<String>,
+    pub target: String,
+    // with --preserve=links, the first copy of each multiply-linked source file
+    copied_links: RefCell<HashMap<(u64, u64), PathBuf>>,
+    // the directories being copied, so following a symlink back up to one is caught
+    ancestors: RefCell<HashSet<(u64, u64)>>,
+    deferred: RefCell<Vec<Deferred>>,
+    failed: Cell<bool>,
+}
+
+pub fn cp_handler(args: Vec<String>) {
+    match Cp::new(args) {
+        Ok(cp) => cp.execute(),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+impl Cp {
+    fn with_cmd(cmd: &'static str) -> Self {
+        Self {
+            cmd,
+            flags: Flags::default(),
+            policy: Policy::new("cp"),
+            report: Report::new(cmd),
+            sources: Vec::new(),
+            target: String::new(),
+            copied_links: RefCell::default(),
+            ancestors: RefCell::default(),
+            deferred: RefCell::default(),
+            failed: Cell::new(false),
+        }
+    }
+
+    // `cp -a` for mv to fall back to across filesystems, where the destination it already
+    // agreed to replace is simply replaced
+    pub fn archive(cmd: &'static str) -> Self {
+        let mut cp = Self::with_cmd(cmd);
+        cp.flags.recursive = true;
+        cp.flags.dereference = Some(Dereference::Never);
+        cp.flags.preserve = Preserve::all();
+        cp.policy.force = true;
+        cp
+    }
+
+    // copies one operand, reporting each error on the way, and tells whether it all worked
+    pub fn copy_all(&self, src: &Path, dest: &Path) -> bool {
+        if let Err(e) = self.copy(src, dest, true) {
+            self.report_error(e);
+        }
+        !self.failed.get()
+    }
+
+    pub fn new(args: Vec<String>) -> Result<Self, String> {
+        let mut cp = Self::with_cmd("cp");
+        let mut operands = Vec::new();
+        let mut only_operands = false;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if only_operands || arg == "-" || !arg.starts_with('-') {
+                operands.push(arg);
+            } else if arg == "--" {
+                only_operands = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                cp.parse_long_flag(long, &mut args)?;
+            } else {
+                cp.parse_flags(&arg, &mut args)?;
+            }
+        }
+
+        cp.target = match operands.len() {
+            0 => return Err("cp: missing file operand".to_string()),
+            1 => {
+                return Err(format!(
+                    "cp: missing destination file operand after '{}'",
+                    operands[0]
+                ));
+            }
+            _ => operands.pop().unwrap_or_default(),
+        };
+        cp.sources = operands;
+
+        if cp.flags.reflink == Reflink::Always && cp.flags.sparse != Sparse::Auto {
+            return Err("cp: --reflink can be used only with --sparse=auto".to_string());
+        }
+        Ok(cp)
+    }
+
+    fn parse_flags(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        for (index, ch) in arg.char_indices().skip(1) {
+            match ch {
+                'S' | 'j' => {
+                    let value = &arg[index + 1..];
+                    let value = if value.is_empty() {
+                        rest.next()
+                    } else {
+                        Some(value.to_string())
+                    };
+                    let Some(value) = value else {
+                        return Err(format!("cp: option requires an argument -- '{}'", ch));
+                    };
+                    if ch == 'S' {
+                        self.policy.set_suffix(&value);
+                    } else {
+                        self.flags.jobs = parse_jobs(&value)?;
+                    }
+                    break;
+                }
+                'r' | 'R' => self.flags.recursive = true,
+                'p' => self
+                    .flags
+                    .preserve
+                    .set("mode,ownership,timestamps", "preserve", true)?,
+                'd' => {
+                    self.flags.dereference = Some(Dereference::Never);
+                    self.flags.preserve.links = true;
+                }
+                'a' => {
+                    self.flags.recursive = true;
+                    self.flags.dereference = Some(Dereference::Never);
+                    self.flags.preserve = Preserve::all();
+                }
+                'v' => self.report.verbose = true,
+                'P' => self.flags.dereference = Some(Dereference::Never),
+                'H' => self.flags.dereference = Some(Dereference::CommandLine),
+                'L' => self.flags.dereference = Some(Dereference::Always),
+                _ if self.policy.short_flag(ch)? => {}
+                _ => return Err(format!("cp: invalid option -- '{}'", ch)),
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_long_flag(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        let (name, mut value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg, None),
+        };
+        if (name == "suffix" || name == "jobs") && value.is_none() {
+            value = rest.next();
+        }
+        if self.policy.long_flag(name, value.as_deref())?
+            || (value.is_none() && self.report.long_flag(name))
+        {
+            return Ok(());
+        }
+
+        match (name, value.as_deref()) {
+            ("recursive", None) => self.flags.recursive = true,
+            ("archive", None) => {
+                self.flags.recursive = true;
+                self.flags.dereference = Some(Dereference::Never);
+                self.flags.preserve = Preserve::all();
+            }
+            ("no-dereference", None) => self.flags.dereference = Some(Dereference::Never),
+            ("dereference", None) => self.flags.dereference = Some(Dereference::Always),
+            ("preserve", None) => {
+                self.flags
+                    .preserve
+                    .set("mode,ownership,timestamps", name, true)?
+            }
+            ("preserve", Some(list)) => self.flags.preserve.set(list, name, true)?,
+            ("no-preserve", Some(list)) => self.flags.preserve.set(list, name, false)?,
+            ("no-preserve", None) => {
+                return Err("cp: option '--no-preserve' requires an argument".to_string());
+            }
+            ("reflink", None) => self.flags.reflink = Reflink::Always,
+            ("reflink", Some(when)) => self.flags.reflink = Reflink::parse(when)?,
+            ("sparse", Some(when)) => self.flags.sparse = Sparse::parse(when)?,
+            ("sparse", None) => {
+                return Err("cp: option '--sparse' requires an argument".to_string());
+            }
+            ("jobs", Some(jobs)) => self.flags.jobs = parse_jobs(jobs)?,
+            ("jobs", None) => {
+                return Err("cp: option '--jobs' requires an argument".to_string());
+            }
+            _ => return Err(format!("cp: unrecognized option '--{}'", arg)),
+        }
+        Ok(())
+    }
+
+    fn execute(&self) {
+        let target = Path::new(&self.target);
+        let into_dir = fs::metadata(target).is_ok_and(|meta| meta.is_dir());
+
+        if !into_dir && self.sources.len() > 1 {
+            eprintln!("cp: target '{}' is not a directory", self.target);
+            return;
+        }
+
+        let sources: Vec<&Path> = self.sources.iter().map(Path::new).collect();
+        self.report.start(&sources, self.follows(true));
+
+        for source in &self.sources {
+            let src = Path::new(source);
+            // an existing directory receives each source under its last component
+            let dest = match src.components().next_back() {
+                Some(name) if into_dir => target.join(name),
+                _ => target.to_path_buf(),
+            };
+
+            if let Err(e) = self.copy(src, &dest, true) {
+                self.report_error(e);
+            }
+        }
+
+        if self.parallel() {
+            self.finish_deferred();
+        }
+        self.report.finish();
+    }
+
+    // errors found while walking wait their turn with -j, so the output does not depend
+    // on which worker finishes first
+    fn report_error(&self, error: String) {
+        self.failed.set(true);
+        let error = match error.strip_prefix("cp: ") {
+            Some(message) if self.cmd != "cp" => format!("{}: {}", self.cmd, message),
+            _ => error,
+        };
+        if self.parallel() {
+            self.defer(Deferred::Error(error));
+        } else {
+            eprintln!("{}", error);
+        }
+    }
+
+    // -v lines of a parallel copy wait with its errors, so both keep the walk's order
+    fn done(&self, src: &Path, dest: &Path) {
+        if self.parallel() {
+            self.defer(Deferred::Done {
+                src: src.to_path_buf(),
+                dest: dest.to_path_buf(),
+            });
+        } else {
+            self.report.done(src, dest);
+        }
+    }
+
+    fn follows(&self, top_level: bool) -> bool {
+        match self.flags.dereference {
+            Some(Dereference::Always) => true,
+            Some(Dereference::CommandLine) => top_level,
+            Some(Dereference::Never) => false,
+            None => !self.flags.recursive,
+        }
+    }
+
+    // `top_level` is set for the operands themselves, which -H dereferences
+    pub fn copy(&self, src: &Path, dest: &Path, top_level: bool) -> Result<(), String> {
+        let stat = if self.follows(top_level) {
+            fs::metadata
+        } else {
+            fs::symlink_metadata
+        };
+        let src_meta = stat(src)
+            .map_err(|e| format!("cp: cannot stat '{}': {}", src.display(), error_message(&e)))?;
+        let dest_meta = stat(dest).ok();
+
+        if let Some(dest_meta) = &dest_meta
+            && dest_meta.dev() == src_meta.dev()
+            && dest_meta.ino() == src_meta.ino()
+        {
+            return Err(format!(
+                "cp: '{}' and '{}' are the same file",
+                src.display(),
+                dest.display()
+            ));
+        }
+
+        if src_meta.is_dir() {
+            if !self.flags.recursive {
+                return Err(format!(
+                    "cp: -r not specified; omitting directory '{}'",
+                    src.display()
+                ));
+            }
+            if is_inside(src, dest) {
+                return Err(format!(
+                    "cp: cannot copy a directory, '{}', into itself, '{}'",
+                    src.display(),
+                    dest.display()
+                ));
+            }
+            if dest_meta.as_ref().is_some_and(|meta| !meta.is_dir()) {
+                return Err(format!(
+                    "cp: cannot overwrite non-directory '{}' with directory '{}'",
+                    dest.display(),
+                    src.display()
+                ));
+            }
+            return self.copy_dir_recursive(src, dest, &src_meta);
+        }
+
+        if dest_meta.as_ref().is_some_and(|meta| meta.is_dir()) {
+            return Err(format!(
+                "cp: cannot overwrite directory '{}' with non-directory",
+                dest.display()
+            ));
+        }
+
+        if !self.policy.allows(&src_meta, dest)? {
+            return Ok(());
+        }
+        if self.link_copy(src, dest, &src_meta)? {
+            return Ok(());
+        }
+
+        // a fifo or device named without -r is read like a file, as GNU cp does
+        let file_type = src_meta.file_type();
+        if file_type.is_file() && self.parallel() {
+            self.defer(Deferred::File {
+                src: src.to_path_buf(),
+                dest: dest.to_path_buf(),
+                meta: src_meta,
+            });
+            return Ok(());
+        }
+        if file_type.is_file() || (!file_type.is_symlink() && !self.flags.recursive) {
+            copy_file(
+                src,
+                dest,
+                &src_meta,
+                &self.flags,
+                self.policy.force,
+                &self.report,
+            )?;
+        } else {
+            if file_type.is_symlink() {
+                copy_symlink(src, dest)?;
+            } else {
+                copy_special(dest, &src_meta)?;
+            }
+            self.report.file_done();
+        }
+        self.flags.preserve.apply(src, dest, &src_meta)?;
+        self.done(src, dest);
+        Ok(())
+    }
+
+    // a file already copied under another name is hard linked to that copy instead
+    fn link_copy(&self, src: &Path, dest: &Path, meta: &Metadata) -> Result<bool, String> {
+        if !self.flags.preserve.links || meta.nlink() < 2 {
+            return Ok(false);
+        }
+
+        let key = (meta.dev(), meta.ino());
+        let mut copied = self.copied_links.borrow_mut();
+        let Some(first) = copied.get(&key) else {
+            copied.insert(key, dest.to_path_buf());
+            return Ok(false);
+        };
+
+        if self.parallel() {
+            self.defer(Deferred::Link {
+                src: src.to_path_buf(),
+                first: first.clone(),
+                dest: dest.to_path_buf(),
+            });
+        } else {
+            hard_link_copy(first, dest)?;
+            self.report.file_done();
+            self.report.done(src, dest);
+        }
+        Ok(true)
+    }
+
+    // a failing entry is reported and the rest of the directory is still copied
+    pub fn copy_dir_recursive(
+        &self,
+        src: &Path,
+        dest: &Path,
+        meta: &Metadata,
+    ) -> Result<(), String> {
+        let key = (meta.dev(), meta.ino());
+        if !self.ancestors.borrow_mut().insert(key) {
+            return Err(format!(
+                "cp: cannot copy cyclic directory '{}'",
+                src.display()
+            ));
+        }
+        let result = self.copy_dir_contents(src, dest, meta);
+        self.ancestors.borrow_mut().remove(&key);
+        result
+    }
+
+    fn copy_dir_contents(&self, src: &Path, dest: &Path, meta: &Metadata) -> Result<(), String> {
+        let created = !dest.exists();
+        if created {
+            // the owner keeps full access so the contents can be written
+            DirBuilder::new()
+                .mode(meta.mode() & 0o777 | 0o700)
+                .create(dest)
+                .map_err(|e| {
+                    format!(
+                        "cp: cannot create directory '{}': {}",
+                        dest.display(),
+                        error_message(&e)
+                    )
+                })?;
+            self.done(src, dest);
+        }
+
+        for child in direct_children(src) {
+            let Some(file_name) = child.file_name() else {
+                continue;
+            };
+
+            if let Err(e) = self.copy(&child, &dest.join(file_name), false) {
+                self.report_error(e);
+            }
+        }
+
+        if self.parallel() {
+            self.defer(Deferred::Dir {
+                src: src.to_path_buf(),
+                dest: dest.to_path_buf(),
+                meta: meta.clone(),
+                created,
+            });
+            return Ok(());
+        }
+        self.finish_dir(src, dest, meta, created)
+    }
+
+    // a directory created with owner access gets the source's permissions back once its
+    // contents are in, less the umask unless the mode is preserved as it is
+    fn finish_dir(
+        &self,
+        src: &Path,
+        dest: &Path,
+        meta: &Metadata,
+        created: bool,
+    ) -> Result<(), String> {
+        if created && !self.flags.preserve.mode {
+            let mode = meta.mode() & 0o777 & !umask();
+            fs::set_permissions(dest, Permissions::from_mode(mode)).map_err(|e| {
+                format!(
+                    "cp: setting permissions for '{}': {}",
+                    dest.display(),
+                    error_message(&e)
+                )
+            })?;
+        }
+        self.flags.preserve.apply(src, dest, meta)
+    }
+}
+
+// a new file gets the source's permissions, an existing one keeps its own; with `force`
+// a destination that cannot be opened is removed and created again
+fn copy_file(
+    src: &Path,
+    dest: &Path,
+    meta: &Metadata,
+    flags: &Flags,
+    force: bool,
+    report: &Report,
+) -> Result<(), String> {
+    let input = File::open(src).map_err(|e| {
+        format!(
+            "cp: cannot open '{}' for reading: {}",
+            src.display(),
+            error_message(&e)
+        )
+    })?;
+    let open = || {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(meta.mode() & 0o777)
+            .open(dest)
+    };
+    let output = match open() {
+        Err(_) if force && fs::symlink_metadata(dest).is_ok() => {
+            fs::remove_file(dest).and_then(|_| open())
+        }
+        result => result,
+    }
+    .map_err(|e| {
+        format!(
+            "cp: cannot create regular file '{}': {}",
+            dest.display(),
+            error_message(&e)
+        )
+    })?;
+
+    // holes and clones are counted as copied once the file is done
+    let mut written = 0;
+    if meta.is_file() && flags.reflink != Reflink::Never {
+        match data::clone(&input, &output) {
+            Ok(()) => {
+                report.advance(meta.len());
+                report.file_done();
+                return Ok(());
+            }
+            Err(e) if flags.reflink == Reflink::Always => {
+                return Err(format!(
+                    "cp: failed to clone '{}' from '{}': {}",
+                    dest.display(),
+                    src.display(),
+                    error_message(&e)
+                ));
+            }
+            Err(_) => {}
+        }
+    }
+
+    let mut progress = |bytes| {
+        written += bytes;
+        report.advance(bytes);
+    };
+    data::copy(&input, &output, meta, flags.sparse, &mut progress).map_err(|e| {
+        format!(
+            "cp: error copying '{}' to '{}': {}",
+            src.display(),
+            dest.display(),
+            error_message(&e)
+        )
+    })?;
+    if meta.is_file() {
+        report.advance(meta.len().saturating_sub(written));
+    }
+    report.file_done();
+    Ok(())
+}
+
+fn parse_jobs(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(jobs) if jobs > 0 => Ok(jobs),
+        _ => Err(format!("cp: invalid number of jobs: '{}'", value)),
+    }
+}
+
+fn hard_link_copy(first: &Path, dest: &Path) -> Result<(), String> {
+    if dest.symlink_metadata().is_ok() {
+        let _ = fs::remove_file(dest);
+    }
+    fs::hard_link(first, dest).map_err(|e| {
+        format!(
+            "cp: cannot create hard link '{}' to '{}': {}",
+            dest.display(),
+            first.display(),
+            error_message(&e)
+        )
+    })
+}
+
+// the copy points at the same place as the original, even if that is relative or dangling
+fn copy_symlink(src: &Path, dest: &Path) -> Result<(), String> {
+    let target = fs::read_link(src).map_err(|e| {
+        format!(
+            "cp: cannot read symbolic link '{}': {}",
+            src.display(),
+            error_message(&e)
+        )
+    })?;
+
+    remove_existing(dest)?;
+    symlink(&target, dest).map_err(|e| {
+        format!(
+            "cp: cannot create symbolic link '{}': {}",
+            dest.display(),
+            error_message(&e)
+        )
+    })
+}
+
+// fifos, sockets and device nodes are recreated, which for devices needs privileges
+fn copy_special(dest: &Path, meta: &Metadata) -> Result<(), String> {
+    remove_existing(dest)?;
+
+    let error = |e: io::Error| {
+        format!(
+            "cp: cannot create special file '{}': {}",
+            dest.display(),
+            error_message(&e)
+        )
+    };
+    let path = CString::new(dest.as_os_str().as_bytes())
+        .map_err(|_| error(io::Error::from(io::ErrorKind::InvalidInput)))?;
+    let mode = meta.mode() & (libc::S_IFMT | 0o777);
+    if unsafe { libc::mknod(path.as_ptr(), mode, meta.rdev()) } != 0 {
+        return Err(error(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+// a destination that is not a directory is replaced rather than written through
+fn remove_existing(dest: &Path) -> Result<(), String> {
+    match fs::symlink_metadata(dest) {
+        Ok(meta) if !meta.is_dir() => fs::remove_file(dest).map_err(|e| {
+            format!(
+                "cp: cannot remove '{}': {}",
+                dest.display(),
+                error_message(&e)
+            )
+        }),
+        _ => Ok(()),
+    }
+}
+
+// the process umask, which can only be read by setting it, so it is put straight back
+fn umask() -> u32 {
+    let mask = unsafe { libc::umask(0) };
+    unsafe { libc::umask(mask) };
+    mask as u32
+}
+
+// compares canonical paths, so `cp -r a a/b` is caught however the paths are spelled
+pub(crate) fn is_inside(dir: &Path, dest: &Path) -> bool {
+    let Ok(dir) = fs::canonicalize(dir) else {
+        return false;
+    };
+    canonical_dest(dest).is_some_and(|dest| dest.starts_with(dir))
+}
+
+// the canonical form of a path that may not exist yet, from its parent
+fn canonical_dest(dest: &Path) -> Option<PathBuf> {
+    if let Ok(path) = fs::canonicalize(dest) {
+        return Some(path);
+    }
+
+    let parent = match dest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(fs::canonicalize(parent).ok()?.join(dest.file_name()?))
+}
@@ -0,0 +1,133 @@
+use std::{
+    fs::Metadata,
+    path::PathBuf,
+    sync::{
+        OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use super::{Cp, copy_file, hard_link_copy};
+
+// what -j leaves for after the walk, in the order a sequential copy would have done it
+#[derive(Debug, Clone)]
+pub enum Deferred {
+    Error(String),
+    // a directory, symlink or special file copied during the walk, for -v
+    Done {
+        src: PathBuf,
+        dest: PathBuf,
+    },
+    // a regular file, copied by the worker pool
+    File {
+        src: PathBuf,
+        dest: PathBuf,
+        meta: Metadata,
+    },
+    // a hard link to a file that may not have been copied yet
+    Link {
+        src: PathBuf,
+        first: PathBuf,
+        dest: PathBuf,
+    },
+    // a directory whose attributes wait until its contents are written
+    Dir {
+        src: PathBuf,
+        dest: PathBuf,
+        meta: Metadata,
+        created: bool,
+    },
+}
+
+impl Cp {
+    pub(super) fn parallel(&self) -> bool {
+        self.flags.jobs > 1
+    }
+
+    pub(super) fn defer(&self, item: Deferred) {
+        self.deferred.borrow_mut().push(item);
+    }
+
+    // copies the files with the pool, then goes through everything in walk order,
+    // printing errors and finishing links and directories now that the files exist
+    pub(super) fn finish_deferred(&self) {
+        let deferred = self.deferred.take();
+        let files: Vec<&Deferred> = deferred
+            .iter()
+            .filter(|item| matches!(item, Deferred::File { .. }))
+            .collect();
+        let mut results = copy_files(&files, self).into_iter();
+
+        // -v lines come out here too, so they keep the walk's order
+        for item in &deferred {
+            let result = match item {
+                Deferred::Error(error) => Err(error.clone()),
+                Deferred::Done { src, dest } => {
+                    self.report.done(src, dest);
+                    Ok(())
+                }
+                // a file without a result is one whose worker panicked
+                Deferred::File { src, dest, .. } => results
+                    .next()
+                    .flatten()
+                    .unwrap_or_else(|| {
+                        Err(format!(
+                            "cp: error copying '{}' to '{}'",
+                            src.display(),
+                            dest.display()
+                        ))
+                    })
+                    .map(|_| self.report.done(src, dest)),
+                Deferred::Link { src, first, dest } => hard_link_copy(first, dest).map(|_| {
+                    self.report.file_done();
+                    self.report.done(src, dest);
+                }),
+                Deferred::Dir {
+                    src,
+                    dest,
+                    meta,
+                    created,
+                } => self.finish_dir(src, dest, meta, *created),
+            };
+            if let Err(e) = result {
+                self.failed.set(true);
+                eprintln!("{}", e);
+            }
+        }
+    }
+}
+
+// each worker takes the next file until none are left and puts its result in that file's
+// slot, so a worker that panics loses only the file it was copying
+fn copy_files(files: &[&Deferred], cp: &Cp) -> Vec<Option<Result<(), String>>> {
+    let next = AtomicUsize::new(0);
+    let (flags, force, report) = (&cp.flags, cp.policy.force, &cp.report);
+    let workers = flags.jobs.min(files.len());
+    let results: Vec<OnceLock<Result<(), String>>> =
+        files.iter().map(|_| OnceLock::new()).collect();
+
+    // a panicked worker is joined here, or the scope would panic in turn
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(Deferred::File { src, dest, meta }) = files.get(index) else {
+                            break;
+                        };
+                        let result = copy_file(src, dest, meta, flags, force, report)
+                            .and_then(|_| flags.preserve.apply(src, dest, meta));
+                        let _ = results[index].set(result);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+    });
+
+    results.into_iter().map(OnceLock::into_inner).collect()
+}
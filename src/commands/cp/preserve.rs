@@ -0,0 +1,165 @@
+use std::{
+    fs::{self, Metadata, Permissions},
+    io,
+    os::unix::fs::{MetadataExt, PermissionsExt, lchown},
+    path::Path,
+};
+
+use filetime::FileTime;
+
+use crate::utils::error_message;
+
+const ATTRIBUTES: [&str; 6] = ["mode", "ownership", "timestamps", "links", "xattr", "all"];
+
+// the attributes --preserve copies from the source onto the copy
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Preserve {
+    pub mode: bool,
+    pub ownership: bool,
+    pub timestamps: bool,
+    pub links: bool,
+    pub xattr: bool,
+}
+
+impl Preserve {
+    // what -p and a bare --preserve keep
+    pub fn basic() -> Self {
+        Self {
+            mode: true,
+            ownership: true,
+            timestamps: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn all() -> Self {
+        Self {
+            links: true,
+            xattr: true,
+            ..Self::basic()
+        }
+    }
+
+    // turns each attribute of a `--preserve` or `--no-preserve` list on or off
+    pub fn set(&mut self, list: &str, option: &str, value: bool) -> Result<(), String> {
+        for name in list.split(',') {
+            match name {
+                "mode" => self.mode = value,
+                "ownership" => self.ownership = value,
+                "timestamps" => self.timestamps = value,
+                "links" => self.links = value,
+                "xattr" => self.xattr = value,
+                "all" => {
+                    *self = if value { Self::all() } else { Self::default() };
+                }
+                _ => {
+                    let valid: Vec<String> = ATTRIBUTES
+                        .iter()
+                        .map(|name| format!("  - '{}'", name))
+                        .collect();
+                    return Err(format!(
+                        "cp: invalid argument '{}' for '--{}'\nValid arguments are:\n{}",
+                        name,
+                        option,
+                        valid.join("\n")
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // ownership goes first since chown clears set-id bits, and times last since the rest
+    // would change the ctime, and for a directory its contents would change the mtime
+    pub fn apply(&self, src: &Path, dest: &Path, meta: &Metadata) -> Result<(), String> {
+        let is_symlink = meta.file_type().is_symlink();
+
+        if self.ownership {
+            preserve_ownership(dest, meta).map_err(|e| {
+                format!(
+                    "cp: failed to preserve ownership for '{}': {}",
+                    dest.display(),
+                    error_message(&e)
+                )
+            })?;
+        }
+
+        if self.mode && !is_symlink {
+            fs::set_permissions(dest, Permissions::from_mode(meta.mode() & 0o7777)).map_err(
+                |e| {
+                    format!(
+                        "cp: preserving permissions for '{}': {}",
+                        dest.display(),
+                        error_message(&e)
+                    )
+                },
+            )?;
+        }
+
+        if self.xattr && !is_symlink {
+            preserve_xattrs(src, dest).map_err(|e| {
+                format!(
+                    "cp: preserving extended attributes for '{}': {}",
+                    dest.display(),
+                    error_message(&e)
+                )
+            })?;
+        }
+
+        if self.timestamps {
+            let atime = FileTime::from_last_access_time(meta);
+            let mtime = FileTime::from_last_modification_time(meta);
+            let result = if is_symlink {
+                filetime::set_symlink_file_times(dest, atime, mtime)
+            } else {
+                filetime::set_file_times(dest, atime, mtime)
+            };
+            result.map_err(|e| {
+                format!(
+                    "cp: preserving times for '{}': {}",
+                    dest.display(),
+                    error_message(&e)
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+// without the right to give files away the group is still worth keeping, and like GNU cp
+// a user who may not change ownership is not told about it
+fn preserve_ownership(dest: &Path, meta: &Metadata) -> io::Result<()> {
+    match lchown(dest, Some(meta.uid()), Some(meta.gid())) {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            match lchown(dest, None, Some(meta.gid())) {
+                Err(err) if err.kind() == io::ErrorKind::PermissionDenied => Ok(()),
+                result => result,
+            }
+        }
+        result => result,
+    }
+}
+
+// ACLs live in `system.posix_acl_*` attributes, so they come along too
+fn preserve_xattrs(src: &Path, dest: &Path) -> io::Result<()> {
+    let names = match xattr::list(src) {
+        Ok(names) => names,
+        Err(err) if err.raw_os_error() == Some(libc::EOPNOTSUPP) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    for name in names {
+        let Some(value) = xattr::get(src, &name)? else {
+            continue;
+        };
+        // only root may set most namespaces besides `user.`, which is not worth failing over
+        let privileged = !name.as_encoded_bytes().starts_with(b"user.");
+        match xattr::set(dest, &name, &value) {
+            Err(err) if err.raw_os_error() == Some(libc::EOPNOTSUPP) => {}
+            Err(err) if privileged && err.kind() == io::ErrorKind::PermissionDenied => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, env};
+
+use super::{Entity, EntityType, get_file_type};
+
+// the colors GNU ls falls back to when LS_COLORS is not set
+const DEFAULT_LS_COLORS: &str = "rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:so=01;35:do=01;35:bd=40;33;01:cd=40;33;01:or=40;31;01:mi=00:su=37;41:sg=30;43:ca=00:tw=30;42:ow=34;42:st=37;44:ex=01;32";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn parse(word: &str) -> Result<Self, String> {
+        match word {
+            "always" | "yes" | "force" => Ok(ColorMode::Always),
+            "never" | "no" | "none" => Ok(ColorMode::Never),
+            "auto" | "tty" | "if-tty" => Ok(ColorMode::Auto),
+            _ => Err(format!(
+                "ls: invalid argument '{}' for '--color'\nValid arguments are:\n  - 'always', 'yes', 'force'\n  - 'never', 'no', 'none'\n  - 'auto', 'tty', 'if-tty'",
+                word
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LsColors {
+    // two-letter indicator keys such as `di` or `ln`
+    codes: HashMap<String, String>,
+    // `*.ext=code` entries, in the order they were given
+    extensions: Vec<(String, String)>,
+}
+
+impl LsColors {
+    pub fn from_env() -> Self {
+        match env::var("LS_COLORS") {
+            Ok(spec) if !spec.is_empty() => Self::parse(&spec),
+            _ => Self::parse(DEFAULT_LS_COLORS),
+        }
+    }
+
+    // parses the `key=value:*.ext=value` format produced by dircolors
+    pub fn parse(spec: &str) -> Self {
+        let mut colors = Self::default();
+
+        for entry in spec.split(':') {
+            let Some((key, value)) = entry.split_once('=') else {
+                continue;
+            };
+            let value = unescape(value);
+
+            if let Some(pattern) = key.strip_prefix('*') {
+                colors.extensions.push((pattern.to_string(), value));
+            } else {
+                colors.codes.insert(key.to_string(), value);
+            }
+        }
+
+        colors
+    }
+
+    pub fn paint_name(&self, entity: &Entity) -> String {
+        match self.entity_code(entity) {
+            Some(code) => self.paint(&entity.name, code),
+            None => entity.name.clone(),
+        }
+    }
+
+    // the `-> target` part of a long listing takes the color of what the link points to
+    pub fn paint_target(&self, entity: &Entity, target: &str) -> String {
+        let code = match entity.target_mode {
+            Some(mode) => self.type_code(&get_file_type(mode), mode, target),
+            None => self.code("mi").or_else(|| self.code("or")),
+        };
+
+        match code {
+            Some(code) => self.paint(target, code),
+            None => target.to_string(),
+        }
+    }
+
+    fn entity_code(&self, entity: &Entity) -> Option<&str> {
+        if entity.file_type != EntityType::SymLink {
+            return self.type_code(&entity.file_type, entity.mode, &entity.name);
+        }
+
+        match entity.target_mode {
+            None => self.code("or").or_else(|| self.code("ln")),
+            Some(mode) if self.code("ln") == Some("target") => {
+                self.type_code(&get_file_type(mode), mode, &entity.name)
+            }
+            Some(_) => self.code("ln"),
+        }
+    }
+
+    fn type_code(&self, file_type: &EntityType, mode: u32, name: &str) -> Option<&str> {
+        let other_writable = mode & 0o002 != 0;
+        let sticky = mode & 0o1000 != 0;
+
+        let key = match file_type {
+            EntityType::Dir if sticky && other_writable => "tw",
+            EntityType::Dir if other_writable => "ow",
+            EntityType::Dir if sticky => "st",
+            EntityType::Dir => "di",
+            EntityType::SymLink => "ln",
+            EntityType::Fifo => "pi",
+            EntityType::Socket => "so",
+            EntityType::BlockDevice => "bd",
+            EntityType::CharacterDevice => "cd",
+            EntityType::File | EntityType::Executable if mode & 0o4000 != 0 => "su",
+            EntityType::File | EntityType::Executable if mode & 0o2000 != 0 => "sg",
+            EntityType::Executable => "ex",
+            EntityType::File => return self.extension_code(name).or_else(|| self.code("fi")),
+            EntityType::None => return None,
+        };
+
+        self.code(key)
+    }
+
+    fn extension_code(&self, name: &str) -> Option<&str> {
+        // an exact-case match wins, otherwise fall back to ignoring case like GNU ls
+        let exact = self
+            .extensions
+            .iter()
+            .rev()
+            .find(|(pattern, _)| name.ends_with(pattern.as_str()));
+        let found = exact.or_else(|| {
+            let lower = name.to_lowercase();
+            self.extensions
+                .iter()
+                .rev()
+                .find(|(pattern, _)| lower.ends_with(&pattern.to_lowercase()))
+        });
+
+        found
+            .map(|(_, code)| code.as_str())
+            .filter(|code| !is_plain(code))
+    }
+
+    fn code(&self, key: &str) -> Option<&str> {
+        self.codes
+            .get(key)
+            .map(String::as_str)
+            .filter(|code| !is_plain(code))
+    }
+
+    fn paint(&self, text: &str, code: &str) -> String {
+        let left = self.codes.get("lc").map(String::as_str).unwrap_or("\x1b[");
+        let right = self.codes.get("rc").map(String::as_str).unwrap_or("m");
+        let end = match self.codes.get("ec") {
+            Some(end) => end.clone(),
+            None => format!(
+                "{}{}{}",
+                left,
+                self.codes.get("rs").map(String::as_str).unwrap_or("0"),
+                right
+            ),
+        };
+
+        format!("{}{}{}{}{}", left, code, right, text, end)
+    }
+}
+
+fn is_plain(code: &str) -> bool {
+    code.is_empty() || code == "0" || code == "00"
+}
+
+// expands the backslash and caret escapes dircolors allows in values
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some('e') => result.push('\x1b'),
+                Some('a') => result.push('\x07'),
+                Some('n') => result.push('\n'),
+                Some('r') => result.push('\r'),
+                Some('t') => result.push('\t'),
+                Some('_') => result.push(' '),
+                Some(digit @ '0'..='7') => {
+                    let mut code = digit.to_digit(8).unwrap_or_default();
+                    for _ in 0..2 {
+                        match chars.peek().and_then(|next| next.to_digit(8)) {
+                            Some(next) => {
+                                code = code * 8 + next;
+                                chars.next();
+                            }
+                            None => break,
+                        }
+                    }
+                    result.push(char::from_u32(code).unwrap_or_default());
+                }
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            },
+            '^' => match chars.next() {
+                Some('?') => result.push('\x7f'),
+                Some(next) => result.push(((next as u8) & 0x1f) as char),
+                None => result.push('^'),
+            },
+            _ => result.push(ch),
+        }
+    }
+
+    result
+}
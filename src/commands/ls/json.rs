@@ -0,0 +1,113 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{Clock, Entity, EntityType, get_file_type_symbols};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormat {
+    // one array holding every entry
+    Array,
+    // one object per line
+    Lines,
+}
+
+// renders an entry as a single-line JSON object; `long_list` must have filled it first
+pub fn entity_json(entity: &Entity, path: &str, clock: &Clock) -> String {
+    let symbol = get_file_type_symbols(entity.file_type.clone()).0;
+    let link_target = entity
+        .link_target
+        .as_ref()
+        .map(|target| target.display().to_string());
+
+    let fields = [
+        ("name", string(&entity.name)),
+        ("path", string(path)),
+        ("type", string(type_name(&entity.file_type))),
+        ("mode", (entity.mode & 0o7777).to_string()),
+        (
+            "permissions",
+            string(&format!("{}{}", symbol, entity.permissions)),
+        ),
+        ("nlink", entity.nlink.to_string()),
+        ("uid", entity.uid.to_string()),
+        ("user", optional(entity.user.as_deref())),
+        ("gid", entity.gid.to_string()),
+        ("group", optional(entity.group.as_deref())),
+        ("size", entity.size.to_string()),
+        ("blocks", entity.blocks.to_string()),
+        ("inode", entity.ino.to_string()),
+        ("modified", string(&timestamp(entity.mtime, clock))),
+        ("accessed", string(&timestamp(entity.atime, clock))),
+        ("changed", string(&timestamp(entity.ctime, clock))),
+        ("link_target", optional(link_target.as_deref())),
+        ("context", optional(entity.context.as_deref())),
+        ("xattrs", attributes(entity.xattrs.as_deref())),
+        ("major", number(entity.major)),
+        ("minor", number(entity.minor)),
+    ];
+
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("\"{}\":{}", key, value))
+        .collect();
+    format!("{{{}}}", body.join(","))
+}
+
+fn type_name(file_type: &EntityType) -> &'static str {
+    match file_type {
+        EntityType::File | EntityType::Executable => "file",
+        EntityType::Dir => "directory",
+        EntityType::SymLink => "symlink",
+        EntityType::CharacterDevice => "char_device",
+        EntityType::BlockDevice => "block_device",
+        EntityType::Fifo => "fifo",
+        EntityType::Socket => "socket",
+        EntityType::None => "unknown",
+    }
+}
+
+fn timestamp((secs, nsecs): (i64, i64), clock: &Clock) -> String {
+    let datetime = DateTime::<Utc>::from_timestamp(secs, nsecs as u32).unwrap_or_default();
+    clock
+        .local(datetime)
+        .to_rfc3339_opts(SecondsFormat::AutoSi, false)
+}
+
+fn optional(value: Option<&str>) -> String {
+    value.map(string).unwrap_or("null".to_string())
+}
+
+// `-@` adds the attribute names and value sizes, otherwise they are left out as null
+fn attributes(xattrs: Option<&[(String, usize)]>) -> String {
+    let Some(xattrs) = xattrs else {
+        return "null".to_string();
+    };
+
+    let items: Vec<String> = xattrs
+        .iter()
+        .map(|(name, size)| format!("{{\"name\":{},\"size\":{}}}", string(name), size))
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn number(value: Option<u32>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or("null".to_string())
+}
+
+fn string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
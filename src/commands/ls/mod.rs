@@ -0,0 +1,1682 @@
+mod colors;
+mod json;
+mod tree;
+mod xattrs;
+
+use crate::utils::{error_message, human_size};
+use chrono::{DateTime, Duration, FixedOffset, Local, Utc};
+use chrono_tz::Tz;
+use colors::{ColorMode, LsColors};
+use core::fmt;
+use json::{JsonFormat, entity_json};
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ffi::{CString, OsStr};
+use std::fs::{self};
+use std::fs::{DirEntry, Metadata};
+use std::io::Error;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::*;
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthStr;
+use users::{get_group_by_gid, get_user_by_uid};
+use xattrs::Xattrs;
+
+pub fn ls_handler(args: Vec<String>, current_path: PathBuf) {
+    let mut ls = match LsConfig::new(args, current_path) {
+        Ok(ls) => ls,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = ls.execute() {
+        eprintln!("ls: write error: {}", e);
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
+struct Target(String, Entity);
+
+#[derive(Debug, Default, Clone)]
+struct Flags {
+    long: bool,
+    all: bool,
+    almost_all: bool,
+    ignore_backups: bool,
+    ignore: Vec<String>,
+    hide: Vec<String>,
+    group_dirs_first: bool,
+    tree: bool,
+    tree_depth: Option<usize>,
+    gitignore: bool,
+    classify: bool,
+    recursive: bool,
+    reverse: bool,
+    format: Format,
+    width: usize,
+    human: Option<u64>,
+    size: bool,
+    inode: bool,
+    context: bool,
+    xattrs: bool,
+    numeric: bool,
+    no_owner: bool,
+    no_group: bool,
+    directory: bool,
+    dereference: Dereference,
+    json: Option<JsonFormat>,
+    color: ColorMode,
+    colors: Option<LsColors>,
+    sort: Sort,
+    time_field: TimeField,
+    time_style: TimeStyle,
+}
+
+impl Flags {
+    // whether entries need a stat call, or readdir's name, type and inode are enough
+    fn needs_metadata(&self) -> bool {
+        self.long
+            || self.size
+            || self.classify
+            || self.json.is_some()
+            || self.colors.is_some()
+            || self.context
+            || self.dereference == Dereference::All
+            || matches!(self.sort, Sort::Time | Sort::Size)
+    }
+
+    // -I and -B always apply, --hide only while hidden entries are hidden
+    fn ignores(&self, name: &OsStr) -> bool {
+        if self.ignore_backups && name.as_bytes().ends_with(b"~") {
+            return true;
+        }
+        let matches = |pattern: &String| fnmatch(pattern, name, libc::FNM_PERIOD);
+        if self.ignore.iter().any(matches) {
+            return true;
+        }
+        !self.all && !self.almost_all && self.hide.iter().any(matches)
+    }
+}
+
+// every long option, so an unambiguous prefix such as `--almost` can be expanded like GNU ls
+const LONG_OPTIONS: [&str; 30] = [
+    "all",
+    "almost-all",
+    "classify",
+    "color",
+    "colour",
+    "context",
+    "dereference",
+    "dereference-command-line",
+    "directory",
+    "format",
+    "full-time",
+    "gitignore",
+    "group-directories-first",
+    "hide",
+    "human-readable",
+    "ignore",
+    "ignore-backups",
+    "inode",
+    "json",
+    "ndjson",
+    "numeric-uid-gid",
+    "recursive",
+    "reverse",
+    "si",
+    "size",
+    "sort",
+    "time",
+    "time-style",
+    "tree",
+    "width",
+];
+
+// user and group names, filesystems without xattr support and the time zone, remembered for
+// the whole run so each uid, gid and mount is looked up only once
+#[derive(Debug, Default, Clone)]
+struct Lookups {
+    users: RefCell<HashMap<u32, Option<String>>>,
+    groups: RefCell<HashMap<u32, Option<String>>>,
+    xattrs: Xattrs,
+    clock: Clock,
+}
+
+// the zone from TZ (falling back to /etc/localtime, then UTC) the way the C library does,
+// and the time the listing started, which decides what counts as recent
+#[derive(Debug, Clone)]
+struct Clock {
+    // None is the system's local time
+    zone: Option<Tz>,
+    now: DateTime<FixedOffset>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        let zone = env::var("TZ").ok().and_then(|tz| {
+            let name = tz.strip_prefix(':').unwrap_or(&tz);
+            name.parse::<Tz>().ok()
+        });
+        let now = Utc::now();
+        let mut clock = Self {
+            zone,
+            now: now.fixed_offset(),
+        };
+        clock.now = clock.local(now);
+        clock
+    }
+}
+
+impl Clock {
+    fn local(&self, datetime: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.zone {
+            Some(zone) => datetime.with_timezone(&zone).fixed_offset(),
+            None => datetime.with_timezone(&Local).fixed_offset(),
+        }
+    }
+}
+
+impl Lookups {
+    fn user(&self, uid: u32) -> Option<String> {
+        self.users
+            .borrow_mut()
+            .entry(uid)
+            .or_insert_with(|| {
+                get_user_by_uid(uid).map(|u| u.name().to_string_lossy().into_owned())
+            })
+            .clone()
+    }
+
+    fn group(&self, gid: u32) -> Option<String> {
+        self.groups
+            .borrow_mut()
+            .entry(gid)
+            .or_insert_with(|| {
+                get_group_by_gid(gid).map(|g| g.name().to_string_lossy().into_owned())
+            })
+            .clone()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    #[default]
+    OneLine,
+    Columns,
+    Across,
+    Commas,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Dereference {
+    #[default]
+    None,
+    // -H: only symlinks named on the command line
+    CommandLine,
+    // -L: every symlink
+    All,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Sort {
+    #[default]
+    Name,
+    Time,
+    Size,
+    Extension,
+    Version,
+    None,
+}
+
+impl Sort {
+    fn parse(word: &str) -> Result<Self, String> {
+        match word {
+            "name" => Ok(Sort::Name),
+            "time" => Ok(Sort::Time),
+            "size" => Ok(Sort::Size),
+            "extension" => Ok(Sort::Extension),
+            "version" => Ok(Sort::Version),
+            "none" => Ok(Sort::None),
+            _ => Err(format!(
+                "ls: invalid argument '{}' for '--sort'\nValid arguments are:\n  - 'none'\n  - 'time'\n  - 'size'\n  - 'extension'\n  - 'version'\n  - 'name'",
+                word
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum TimeField {
+    #[default]
+    Modified,
+    Accessed,
+    Changed,
+}
+
+impl TimeField {
+    fn parse(word: &str) -> Result<Self, String> {
+        match word {
+            "mtime" | "modification" => Ok(TimeField::Modified),
+            "atime" | "access" | "use" => Ok(TimeField::Accessed),
+            "ctime" | "status" => Ok(TimeField::Changed),
+            _ => Err(format!(
+                "ls: invalid argument '{}' for '--time'\nValid arguments are:\n  - 'atime', 'access', 'use'\n  - 'ctime', 'status'\n  - 'mtime', 'modification'",
+                word
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+enum TimeStyle {
+    #[default]
+    Locale,
+    FullIso,
+    LongIso,
+    Iso,
+    // (format for old files, format for recent files)
+    Format(String, String),
+}
+
+impl TimeStyle {
+    fn parse(style: &str) -> Result<Self, String> {
+        if let Some(format) = style.strip_prefix('+') {
+            let (old, recent) = format.split_once('\n').unwrap_or((format, format));
+            return Ok(TimeStyle::Format(old.to_string(), recent.to_string()));
+        }
+
+        match style.strip_prefix("posix-").unwrap_or(style) {
+            "full-iso" => Ok(TimeStyle::FullIso),
+            "long-iso" => Ok(TimeStyle::LongIso),
+            "iso" => Ok(TimeStyle::Iso),
+            "locale" => Ok(TimeStyle::Locale),
+            _ => Err(format!(
+                "ls: invalid argument '{}' for '--time-style'\nValid arguments are:\n  - [posix-]full-iso\n  - [posix-]long-iso\n  - [posix-]iso\n  - [posix-]locale\n  - +FORMAT (e.g., +%H:%M) for a 'date'-style format",
+                style
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct LsConfig {
+    current_path: PathBuf,
+    valid_flags: Vec<char>,
+    flags: Flags,
+    targets: Vec<Target>,
+    targets_len: usize,
+    json_count: Cell<usize>,
+    lookups: Lookups,
+}
+
+impl LsConfig {
+    fn new(args: Vec<String>, current_path: PathBuf) -> Result<Self, String> {
+        let valid_flags = [
+            'l', 'a', 'F', 'R', 't', 'S', 'r', 'X', 'U', 'v', 'u', 'c', '1', 'C', 'x', 'm', 'w',
+            'h', 's', 'i', 'n', 'g', 'o', 'd', 'L', 'H', 'Z', '@', 'A', 'B', 'I',
+        ]
+        .into_iter()
+        .collect();
+        let mut ls = Self {
+            flags: Flags::default(),
+            current_path,
+            targets: Vec::new(),
+            valid_flags,
+            targets_len: 0,
+            json_count: Cell::new(0),
+            lookups: Lookups::default(),
+        };
+
+        if io::stdout().is_terminal() {
+            ls.flags.format = Format::Columns;
+        }
+        ls.flags.width = terminal_width();
+
+        if let Ok(style) = env::var("TIME_STYLE") {
+            ls.flags.time_style = TimeStyle::parse(&style)?;
+        }
+
+        // -L is the depth limit in a tree, as in `tree`, so --tree must be known before -L is read
+        ls.flags.tree = args
+            .iter()
+            .take_while(|arg| *arg != "--")
+            .filter_map(|arg| arg.strip_prefix("--"))
+            .any(|long| {
+                let name = long.split_once('=').map_or(long, |(name, _)| name);
+                resolve_long_option(name, long) == Ok("tree")
+            });
+
+        let mut target_args: Vec<String> = Vec::new();
+        let mut flag_as_target = false;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if flag_as_target || arg == "-" || !arg.starts_with('-') {
+                target_args.push(arg);
+            } else if arg == "--" {
+                flag_as_target = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                ls.parse_long_flag(long, &mut args)?;
+            } else {
+                ls.parse_flags(&arg, &mut args)?;
+            }
+        }
+        ls.targets_len = target_args.len();
+
+        // -u and -c alone sort by that time; with -l they only change the time shown
+        if ls.flags.time_field != TimeField::Modified
+            && !ls.flags.long
+            && ls.flags.sort == Sort::Name
+        {
+            ls.flags.sort = Sort::Time;
+        }
+
+        let use_color = match ls.flags.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => io::stdout().is_terminal(),
+        };
+        if use_color {
+            ls.flags.colors = Some(LsColors::from_env());
+        }
+        ls.lookups.xattrs.follow = ls.flags.dereference == Dereference::All;
+
+        ls.parse_targets(target_args);
+        Ok(ls)
+    }
+
+    fn parse_flags(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        for (index, ch) in arg.char_indices().skip(1) {
+            if !self.valid_flags.contains(&ch) {
+                return Err(format!("ls: invalid option -- '{}'", ch));
+            }
+            match ch {
+                // the rest of the word, or the next argument, is the option's value
+                'w' | 'I' | 'L' if ch != 'L' || self.flags.tree => {
+                    let value = &arg[index + 1..];
+                    let value = if value.is_empty() {
+                        rest.next()
+                    } else {
+                        Some(value.to_string())
+                    };
+                    let Some(value) = value else {
+                        return Err(format!("ls: option requires an argument -- '{}'", ch));
+                    };
+                    match ch {
+                        'w' => self.flags.width = parse_width(&value)?,
+                        'L' => self.flags.tree_depth = Some(tree::parse_depth(&value)?),
+                        _ => self.flags.ignore.push(value),
+                    }
+                    break;
+                }
+                'l' => self.flags.long = true,
+                'd' => self.flags.directory = true,
+                'L' => self.flags.dereference = Dereference::All,
+                'H' => self.flags.dereference = Dereference::CommandLine,
+                'h' => self.flags.human = Some(1024),
+                's' => self.flags.size = true,
+                'i' => self.flags.inode = true,
+                'Z' => self.flags.context = true,
+                '@' => self.flags.xattrs = true,
+                'n' => {
+                    self.flags.long = true;
+                    self.flags.numeric = true;
+                }
+                'g' => {
+                    self.flags.long = true;
+                    self.flags.no_owner = true;
+                }
+                'o' => {
+                    self.flags.long = true;
+                    self.flags.no_group = true;
+                }
+                '1' => self.set_format(Format::OneLine),
+                'C' => self.set_format(Format::Columns),
+                'x' => self.set_format(Format::Across),
+                'm' => self.set_format(Format::Commas),
+                'a' => self.set_all(true),
+                'A' => self.set_all(false),
+                'B' => self.flags.ignore_backups = true,
+                'F' => self.flags.classify = true,
+                'R' => self.flags.recursive = true,
+                'r' => self.flags.reverse = true,
+                't' => self.flags.sort = Sort::Time,
+                'S' => self.flags.sort = Sort::Size,
+                'X' => self.flags.sort = Sort::Extension,
+                'U' => self.flags.sort = Sort::None,
+                'v' => self.flags.sort = Sort::Version,
+                'u' => self.flags.time_field = TimeField::Accessed,
+                'c' => self.flags.time_field = TimeField::Changed,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // -1, -C, -x and -m override an earlier -l, as the last format given wins
+    fn set_format(&mut self, format: Format) {
+        self.flags.long = false;
+        self.flags.format = format;
+    }
+
+    // -a and -A override each other, as the last one given wins
+    fn set_all(&mut self, with_dots: bool) {
+        self.flags.all = with_dots;
+        self.flags.almost_all = !with_dots;
+    }
+
+    fn parse_long_flag(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg, None),
+        };
+        let name = resolve_long_option(name, arg)?;
+
+        let takes_value = matches!(
+            name,
+            "color"
+                | "colour"
+                | "width"
+                | "format"
+                | "sort"
+                | "time"
+                | "time-style"
+                | "ignore"
+                | "hide"
+        );
+        if !takes_value && value.is_some() {
+            return Err(format!("ls: option '--{}' doesn't allow an argument", name));
+        }
+
+        match name {
+            "all" => self.set_all(true),
+            "almost-all" => self.set_all(false),
+            "ignore-backups" => self.flags.ignore_backups = true,
+            "group-directories-first" => self.flags.group_dirs_first = true,
+            "tree" => self.flags.tree = true,
+            "gitignore" => self.flags.gitignore = true,
+            "classify" => self.flags.classify = true,
+            "ignore" | "hide" => {
+                let Some(pattern) = value.or_else(|| rest.next()) else {
+                    return Err(format!("ls: option '--{}' requires an argument", name));
+                };
+                if name == "ignore" {
+                    self.flags.ignore.push(pattern);
+                } else {
+                    self.flags.hide.push(pattern);
+                }
+            }
+            "color" | "colour" => {
+                self.flags.color = match value {
+                    Some(value) => ColorMode::parse(&value)?,
+                    None => ColorMode::Always,
+                };
+            }
+            "recursive" => self.flags.recursive = true,
+            "directory" => self.flags.directory = true,
+            "json" => self.flags.json = Some(JsonFormat::Array),
+            "ndjson" => self.flags.json = Some(JsonFormat::Lines),
+            "dereference" => self.flags.dereference = Dereference::All,
+            "dereference-command-line" => self.flags.dereference = Dereference::CommandLine,
+            "human-readable" => self.flags.human = Some(1024),
+            "si" => self.flags.human = Some(1000),
+            "size" => self.flags.size = true,
+            "inode" => self.flags.inode = true,
+            "context" => self.flags.context = true,
+            "numeric-uid-gid" => {
+                self.flags.long = true;
+                self.flags.numeric = true;
+            }
+            "width" | "format" => {
+                let value = match value.or_else(|| rest.next()) {
+                    Some(value) => value,
+                    None => {
+                        return Err(format!("ls: option '--{}' requires an argument", name));
+                    }
+                };
+                if name == "width" {
+                    self.flags.width = parse_width(&value)?;
+                } else {
+                    match value.as_str() {
+                        "long" | "verbose" => self.flags.long = true,
+                        "single-column" => self.set_format(Format::OneLine),
+                        "vertical" => self.set_format(Format::Columns),
+                        "across" | "horizontal" => self.set_format(Format::Across),
+                        "commas" => self.set_format(Format::Commas),
+                        _ => {
+                            return Err(format!("ls: invalid argument '{}' for '--format'", value));
+                        }
+                    }
+                }
+            }
+            "reverse" => self.flags.reverse = true,
+            "sort" | "time" => {
+                let value = match value.or_else(|| rest.next()) {
+                    Some(value) => value,
+                    None => {
+                        return Err(format!("ls: option '--{}' requires an argument", name));
+                    }
+                };
+                if name == "sort" {
+                    self.flags.sort = Sort::parse(&value)?;
+                } else {
+                    self.flags.time_field = TimeField::parse(&value)?;
+                }
+            }
+            "full-time" => {
+                self.flags.long = true;
+                self.flags.time_style = TimeStyle::FullIso;
+            }
+            "time-style" => {
+                let value = match value.or_else(|| rest.next()) {
+                    Some(value) => value,
+                    None => {
+                        return Err("ls: option '--time-style' requires an argument".to_string());
+                    }
+                };
+                self.flags.time_style = TimeStyle::parse(&value)?;
+            }
+            _ => return Err(format!("ls: unrecognized option '--{}'", arg)),
+        }
+        Ok(())
+    }
+
+    fn absolute_path(&self, path: String) -> PathBuf {
+        let path = if !path.starts_with("/") {
+            format!("{}/{}", self.current_path.display(), path)
+        } else {
+            path.clone()
+        };
+
+        PathBuf::from(path)
+    }
+
+    fn parse_targets(&mut self, args: Vec<String>) {
+        if args.is_empty() {
+            let current = PathBuf::from(format!("{}/.", self.current_path.display()));
+            let follow = self.flags.dereference != Dereference::None;
+            let current_dir = match Entity::new(current, follow) {
+                Ok(mut entity) => {
+                    entity.name = ".".to_string();
+                    entity
+                }
+                Err(err) => {
+                    handle_ls_erros(err, ".".to_string());
+                    return;
+                }
+            };
+
+            self.targets.push(Target(".".to_string(), current_dir));
+            return;
+        }
+
+        let follow = self.flags.dereference != Dereference::None;
+        for elem in args {
+            let abs_path = self.absolute_path(elem.clone());
+            match Entity::new(abs_path, follow) {
+                Ok(mut entity) => {
+                    // operands listed as entries show the path as it was typed
+                    entity.name = elem.clone();
+                    let target = Target(elem, entity);
+                    self.targets.push(target);
+                }
+                Err(err) => {
+                    handle_ls_erros(err, elem);
+                }
+            };
+        }
+
+        // order targets by files then folders, unless -d lists folders as files
+        self.targets.sort_by(|a, b| {
+            let is_a_dir = a.1.file_type == EntityType::Dir && !self.flags.directory;
+            let is_b_dir = b.1.file_type == EntityType::Dir && !self.flags.directory;
+            if is_a_dir != is_b_dir {
+                is_a_dir.cmp(&is_b_dir)
+            } else {
+                compare_entities(&a.1, &b.1, &a.0, &b.0, &self.flags)
+            }
+        });
+    }
+
+    // output is buffered and only flushed before a directory is read, so errors stay in order
+    fn execute(&mut self) -> io::Result<()> {
+        let targets = std::mem::take(&mut self.targets);
+        let mut ancestors = HashSet::new();
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        // file operands are gathered so they share one grid, like GNU ls
+        let mut files = List::new(String::new());
+        let mut printed = false;
+
+        if self.flags.tree {
+            self.print_tree(&mut out, targets)?;
+            return out.flush();
+        }
+
+        if self.flags.json == Some(JsonFormat::Array) {
+            write!(out, "[")?;
+        }
+
+        for mut target in targets {
+            out.flush()?;
+            let mut list = List::new(target.0);
+            if !list.get_items(&mut target.1, &self.flags) {
+                files.items.append(&mut list.items);
+                continue;
+            }
+
+            if !files.items.is_empty() {
+                self.print_items(&mut out, &mut files)?;
+                files.items.clear();
+                printed = true;
+            }
+
+            if printed && self.flags.json.is_none() {
+                writeln!(out)?;
+            }
+            self.print_list(&mut out, list, &mut target.1, &mut ancestors)?;
+            printed = true;
+        }
+
+        if !files.items.is_empty() {
+            self.print_items(&mut out, &mut files)?;
+        }
+
+        if self.flags.json == Some(JsonFormat::Array) {
+            writeln!(out, "]")?;
+        }
+        out.flush()
+    }
+
+    fn print_json(&self, out: &mut impl Write, list: &mut List) -> io::Result<()> {
+        for file in list.items.iter_mut() {
+            file.long_list(&self.flags, &self.lookups);
+
+            let path = if list.header.is_empty() {
+                file.name.clone()
+            } else {
+                child_path(&list.header, &file.name)
+            };
+            let json = entity_json(file, &path, &self.lookups.clock);
+
+            match self.flags.json {
+                Some(JsonFormat::Array) => {
+                    let separator = if self.json_count.get() > 0 { "," } else { "" };
+                    write!(out, "{}\n{}", separator, json)?;
+                    self.json_count.set(self.json_count.get() + 1);
+                }
+                _ => writeln!(out, "{}", json)?,
+            }
+        }
+        Ok(())
+    }
+
+    // prints the list in two passes: the first fills every entry and measures the columns
+    fn print_items(&self, out: &mut impl Write, list: &mut List) -> io::Result<()> {
+        if self.flags.json.is_some() {
+            return self.print_json(out, list);
+        }
+
+        for file in list.items.iter_mut() {
+            self.decorate(file);
+            if self.flags.long {
+                file.long_list(&self.flags, &self.lookups);
+            } else if self.flags.context {
+                file.context = self.lookups.xattrs.context(file);
+            }
+        }
+
+        let widths = Widths::measure(&list.items, &self.flags);
+
+        if self.flags.long {
+            for file in list.items.iter() {
+                writeln!(out, "{}{}", file.long_columns(&widths, &self.flags), file)?;
+                for (name, size) in file.xattrs.iter().flatten() {
+                    writeln!(out, "\t{}\t{:>8}", name, size)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let cells: Vec<(String, usize)> = list
+            .items
+            .iter()
+            .map(|file| {
+                let prefix = file.prefix_columns(&widths, &self.flags);
+                let width = prefix.len() + file.name.width() + file.suffix().len();
+                (format!("{}{}", prefix, file), width)
+            })
+            .collect();
+        print_grid(out, &cells, self.flags.format, self.flags.width)
+    }
+
+    // the -F indicator and colors, which every output but JSON shares
+    fn decorate(&self, file: &mut Entity) {
+        file.is_classified = self.flags.classify;
+        if let Some(colors) = &self.flags.colors {
+            file.painted_name = Some(colors.paint_name(file));
+            if let Some(target) = &file.link_target {
+                let target = target.display().to_string();
+                file.painted_target = Some(colors.paint_target(file, &target));
+            }
+        }
+    }
+
+    fn print_list(
+        &self,
+        out: &mut impl Write,
+        mut list: List,
+        target: &mut Entity,
+        ancestors: &mut HashSet<(u64, u64)>,
+    ) -> io::Result<()> {
+        let text = self.flags.json.is_none();
+        if text && (self.targets_len > 1 || self.flags.recursive) {
+            writeln!(out, "{}:", list.header)?;
+        }
+
+        if text && (self.flags.long || self.flags.size) {
+            writeln!(out, "total {}", format_blocks(list.total, &self.flags))?;
+        }
+
+        self.print_items(out, &mut list)?;
+
+        if !self.flags.recursive {
+            return Ok(());
+        }
+
+        // only the directories being descended through are tracked, so a symlink back up to
+        // one of them is a loop while the same directory reached again elsewhere is listed again
+        let key = fs::metadata(&target.path)
+            .ok()
+            .map(|meta| (meta.dev(), meta.ino()))
+            .filter(|key| ancestors.insert(*key));
+
+        for item in list.items.iter_mut() {
+            if item.file_type != EntityType::Dir || item.name == "." || item.name == ".." {
+                continue;
+            }
+
+            let header = child_path(&list.header, &item.name);
+
+            out.flush()?;
+            if ancestors.contains(&(item.dev, item.ino)) {
+                eprintln!("ls: {}: not listing already-listed directory", header);
+                continue;
+            }
+            // like GNU ls, a directory that cannot be read gets no separator, only its error
+            let mut sub_list = List::new(header);
+            if sub_list.get_items(item, &self.flags) {
+                if text {
+                    writeln!(out)?;
+                }
+                self.print_list(out, sub_list, item, ancestors)?;
+            }
+        }
+
+        if let Some(key) = key {
+            ancestors.remove(&key);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct List {
+    header: String,
+    total: u64,
+    items: Vec<Entity>,
+}
+
+impl List {
+    fn new(header: String) -> Self {
+        Self {
+            header,
+            ..Default::default()
+        }
+    }
+
+    // returns whether the target was listed as a directory
+    fn get_items(&mut self, target: &mut Entity, flags: &Flags) -> bool {
+        let end_with_slash = target.path.display().to_string().ends_with("/");
+        let is_symlink = target.file_type == EntityType::SymLink;
+        let mut is_dir = false;
+
+        if flags.directory && !flags.tree {
+            self.items.push(target.clone());
+            return false;
+        }
+
+        let entries = match fs::read_dir(&target.path) {
+            Ok(res) => {
+                if is_symlink && (flags.classify || flags.long) {
+                    if end_with_slash {
+                        is_dir = true
+                    }
+                } else {
+                    is_dir = true;
+                }
+                res
+            }
+            Err(err) if err.raw_os_error() == Some(libc::ENOTDIR) || is_symlink => {
+                self.items.push(target.clone());
+                return false;
+            }
+            Err(err) => {
+                handle_ls_erros(err, self.header.clone());
+                return false;
+            }
+        };
+
+        if !is_dir {
+            self.items.push(target.clone());
+            return false;
+        }
+
+        if flags.all {
+            let follow = flags.dereference == Dereference::All;
+            for (path, name) in [(&target.path, "."), (&target.parent, "..")] {
+                match Entity::new(path.clone(), follow) {
+                    Ok(mut entity) => {
+                        entity.name = name.to_string();
+                        self.total += entity.blocks;
+                        self.items.push(entity);
+                    }
+                    Err(err) => handle_ls_erros(err, name.to_string()),
+                }
+            }
+        }
+
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let hidden = name.as_bytes().starts_with(b".") && !flags.all && !flags.almost_all;
+            if hidden || flags.ignores(&name) {
+                continue;
+            }
+
+            match Entity::from_dir_entry(&entry, flags) {
+                Ok(entity) => {
+                    self.total += entity.blocks;
+                    self.items.push(entity);
+                }
+                Err(err) => handle_ls_erros(err, entry.file_name().display().to_string()),
+            };
+        }
+
+        self.items
+            .sort_by(|a, b| compare_entities(a, b, &a.name, &b.name, flags));
+        true
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+enum EntityType {
+    File,
+    Dir,
+    Executable,
+    SymLink,
+    CharacterDevice,
+    BlockDevice,
+    Fifo,
+    Socket,
+    #[default]
+    None,
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+struct Entity {
+    parent: PathBuf,
+    path: PathBuf,
+    file_type: EntityType,
+    permissions: String,
+    nlink: u64,
+    uid: u32,
+    gid: u32,
+    user: Option<String>,
+    group: Option<String>,
+    minor: Option<u32>,
+    major: Option<u32>,
+    size: u64,
+    time: String,
+    name: String,
+    blocks: u64,
+    mtime: (i64, i64),
+    atime: (i64, i64),
+    ctime: (i64, i64),
+    dev: u64,
+    ino: u64,
+    mode: u32,
+    link_target: Option<PathBuf>,
+    // the mode of what a symlink points to, stat'ed once for -F and colors; None when dangling
+    target_mode: Option<u32>,
+    context: Option<String>,
+    xattrs: Option<Vec<(String, usize)>>,
+    painted_name: Option<String>,
+    painted_target: Option<String>,
+    is_classified: bool,
+    is_long: bool,
+}
+
+impl Entity {
+    // with `follow`, a symlink describes what it points to, unless it is dangling
+    fn new(path: PathBuf, follow: bool) -> Result<Self, Error> {
+        let metadata = entity_metadata(&path, follow)?;
+        let mut new = Self {
+            parent: get_parent(path.clone()),
+            path: path.clone(),
+            name: path.file_name().unwrap_or_default().display().to_string(),
+            blocks: metadata.blocks().div_ceil(2),
+            size: metadata.len(),
+            mtime: (metadata.mtime(), metadata.mtime_nsec()),
+            atime: (metadata.atime(), metadata.atime_nsec()),
+            ctime: (metadata.ctime(), metadata.ctime_nsec()),
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            mode: metadata.mode(),
+            nlink: metadata.nlink(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            file_type: get_file_type(metadata.mode()),
+            ..Default::default()
+        };
+
+        if matches!(
+            new.file_type,
+            EntityType::CharacterDevice | EntityType::BlockDevice
+        ) {
+            new.major = Some(libc::major(metadata.rdev()));
+            new.minor = Some(libc::minor(metadata.rdev()));
+        }
+        new.link_target = read_link(&new.path, &new.file_type);
+        if new.file_type == EntityType::SymLink {
+            new.target_mode = fs::metadata(&new.path).ok().map(|meta| meta.mode());
+        }
+        Ok(new)
+    }
+
+    // builds an entry from what readdir already knows, and only stats it when the listing needs more
+    fn from_dir_entry(entry: &DirEntry, flags: &Flags) -> Result<Self, Error> {
+        let file_type = entry.file_type()?;
+        if flags.needs_metadata() || ((flags.recursive || flags.tree) && file_type.is_dir()) {
+            return Self::new(entry.path(), flags.dereference == Dereference::All);
+        }
+
+        let path = entry.path();
+        Ok(Self {
+            parent: get_parent(path.clone()),
+            name: entry.file_name().display().to_string(),
+            file_type: get_dirent_type(file_type),
+            ino: entry.ino(),
+            path,
+            ..Default::default()
+        })
+    }
+
+    fn time(&self, field: TimeField) -> (i64, i64) {
+        match field {
+            TimeField::Modified => self.mtime,
+            TimeField::Accessed => self.atime,
+            TimeField::Changed => self.ctime,
+        }
+    }
+
+    fn long_list(&mut self, flags: &Flags, lookups: &Lookups) {
+        self.is_long = true;
+        self.permissions = get_permissions(self.mode);
+
+        // like GNU ls, `+` marks an ACL and `.` a security context without one
+        self.context = lookups.xattrs.context(self);
+        if lookups.xattrs.has_acl(self) {
+            self.permissions.push('+');
+        } else if self.context.is_some() {
+            self.permissions.push('.');
+        }
+
+        if flags.xattrs {
+            self.xattrs = Some(lookups.xattrs.list(self));
+        }
+
+        if !flags.numeric {
+            self.user = lookups.user(self.uid);
+            self.group = lookups.group(self.gid);
+        }
+
+        self.time = get_time(
+            self.time(flags.time_field),
+            &flags.time_style,
+            &lookups.clock,
+        );
+    }
+}
+
+// the name column: colored name, link target in long format, and the -F indicator
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut name = self.painted_name.clone().unwrap_or(self.name.clone());
+
+        if self.is_long
+            && let Some(link_target) = self.link_target.clone()
+        {
+            let target = link_target.display().to_string();
+            let target = self.painted_target.clone().unwrap_or(target);
+            name = format!("{} -> {}", name, target);
+        }
+
+        write!(f, "{}{}", name, self.suffix())
+    }
+}
+
+// column widths shared by every entry of a list, so long names or big sizes keep it aligned
+#[derive(Debug, Default)]
+struct Widths {
+    inode: usize,
+    blocks: usize,
+    context: usize,
+    mode: usize,
+    nlink: usize,
+    user: usize,
+    group: usize,
+    size: usize,
+    major: usize,
+    minor: usize,
+}
+
+impl Widths {
+    fn measure(items: &[Entity], flags: &Flags) -> Self {
+        let mut widths = Self::default();
+
+        for file in items {
+            widths.inode = widths.inode.max(file.ino.to_string().len());
+            widths.blocks = widths.blocks.max(format_blocks(file.blocks, flags).len());
+            widths.context = widths.context.max(file.context_label().width());
+            if !flags.long {
+                continue;
+            }
+
+            widths.mode = widths.mode.max(file.permissions.chars().count() + 1);
+            widths.nlink = widths.nlink.max(file.nlink.to_string().len());
+            widths.user = widths.user.max(file.owner().len());
+            widths.group = widths.group.max(file.group().len());
+
+            if let (Some(major), Some(minor)) = (file.major, file.minor) {
+                widths.major = widths.major.max(major.to_string().len());
+                widths.minor = widths.minor.max(minor.to_string().len());
+            } else {
+                widths.size = widths.size.max(format_size(file.size, flags).len());
+            }
+        }
+
+        if widths.major > 0 {
+            widths.size = widths.size.max(widths.major + 2 + widths.minor);
+        }
+        widths
+    }
+}
+
+impl Entity {
+    fn owner(&self) -> String {
+        self.user.clone().unwrap_or(self.uid.to_string())
+    }
+
+    fn group(&self) -> String {
+        self.group.clone().unwrap_or(self.gid.to_string())
+    }
+
+    fn context_label(&self) -> &str {
+        self.context.as_deref().unwrap_or("?")
+    }
+
+    // the -i and -s columns, which also precede names in the short formats, as does -Z
+    fn prefix_columns(&self, widths: &Widths, flags: &Flags) -> String {
+        let mut prefix = String::new();
+        if flags.inode {
+            prefix.push_str(&format!("{:>w$} ", self.ino, w = widths.inode));
+        }
+        if flags.size {
+            let blocks = format_blocks(self.blocks, flags);
+            prefix.push_str(&format!("{:>w$} ", blocks, w = widths.blocks));
+        }
+        if flags.context && !flags.long {
+            prefix.push_str(&self.context_column(widths));
+        }
+        prefix
+    }
+
+    fn context_column(&self, widths: &Widths) -> String {
+        let label = self.context_label();
+        format!(
+            "{}{} ",
+            label,
+            " ".repeat(widths.context.saturating_sub(label.width()))
+        )
+    }
+
+    fn long_columns(&self, widths: &Widths, flags: &Flags) -> String {
+        let symbol = get_file_type_symbols(self.file_type.clone()).0;
+        let mode = format!("{}{}", symbol, self.permissions);
+        let mut line = self.prefix_columns(widths, flags);
+
+        line.push_str(&format!(
+            "{:<mw$} {:>nw$} ",
+            mode,
+            self.nlink,
+            mw = widths.mode,
+            nw = widths.nlink
+        ));
+
+        // names are left aligned, bare ids are right aligned like GNU ls
+        if !flags.no_owner {
+            match &self.user {
+                Some(user) => line.push_str(&format!("{:<w$} ", user, w = widths.user)),
+                None => line.push_str(&format!("{:>w$} ", self.uid, w = widths.user)),
+            }
+        }
+        if !flags.no_group {
+            match &self.group {
+                Some(group) => line.push_str(&format!("{:<w$} ", group, w = widths.group)),
+                None => line.push_str(&format!("{:>w$} ", self.gid, w = widths.group)),
+            }
+        }
+        if flags.context {
+            line.push_str(&self.context_column(widths));
+        }
+
+        let size = match (self.major, self.minor) {
+            (Some(major), Some(minor)) => format!(
+                "{:>mw$}, {:>nw$}",
+                major,
+                minor,
+                mw = widths.major,
+                nw = widths.minor
+            ),
+            _ => format_size(self.size, flags),
+        };
+        line.push_str(&format!("{:>w$} {} ", size, self.time, w = widths.size));
+        line
+    }
+
+    // the -F indicator printed after the name
+    fn suffix(&self) -> &'static str {
+        if !self.is_classified {
+            return "";
+        }
+
+        let mut sufix = get_file_type_symbols(self.file_type.clone()).1;
+        if self.file_type == EntityType::SymLink {
+            match self.target_mode {
+                Some(mode) => {
+                    if self.is_long {
+                        sufix = get_file_type_symbols(get_file_type(mode)).1
+                    }
+                }
+                None => sufix = "",
+            };
+        }
+        sufix
+    }
+}
+
+// each cell is the text to print and its width on screen, which excludes color codes
+fn print_grid(
+    out: &mut impl Write,
+    cells: &[(String, usize)],
+    format: Format,
+    line_width: usize,
+) -> io::Result<()> {
+    if cells.is_empty() {
+        return Ok(());
+    }
+
+    let names: Vec<&String> = cells.iter().map(|(name, _)| name).collect();
+    let widths: Vec<usize> = cells.iter().map(|(_, width)| *width).collect();
+
+    match format {
+        Format::OneLine => {
+            for name in names {
+                writeln!(out, "{}", name)?;
+            }
+        }
+        Format::Commas => {
+            let mut pos = 0;
+            let mut line = String::new();
+            for (index, name) in names.iter().enumerate() {
+                let is_last = index + 1 == names.len();
+                let len = widths[index] + if is_last { 0 } else { 1 };
+                if index > 0 {
+                    if line_width > 0 && pos + 1 + len > line_width {
+                        line.push('\n');
+                        pos = 0;
+                    } else {
+                        line.push(' ');
+                        pos += 1;
+                    }
+                }
+                line.push_str(name);
+                if !is_last {
+                    line.push(',');
+                }
+                pos += len;
+            }
+            writeln!(out, "{}", line)?;
+        }
+        Format::Columns | Format::Across => {
+            let across = format == Format::Across;
+            let (rows, column_widths) = grid_layout(&widths, across, line_width);
+            let cols = column_widths.len();
+
+            for row in 0..rows {
+                let mut line = String::new();
+                for (col, column_width) in column_widths.iter().enumerate() {
+                    let index = if across {
+                        row * cols + col
+                    } else {
+                        col * rows + row
+                    };
+                    let Some(name) = names.get(index) else {
+                        break;
+                    };
+
+                    line.push_str(name);
+                    let next = if across { index + 1 } else { index + rows };
+                    if col + 1 < cols && next < names.len() {
+                        line.push_str(&" ".repeat(column_width - widths[index]));
+                    }
+                }
+                writeln!(out, "{}", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// finds the most columns that fit the line, returning the row count and each column's width;
+// like GNU ls, every column counts as at least MIN_COLUMN_WIDTH even when it is left empty
+fn grid_layout(widths: &[usize], across: bool, line_width: usize) -> (usize, Vec<usize>) {
+    const SEPARATOR: usize = 2;
+    const MIN_COLUMN_WIDTH: usize = 1 + SEPARATOR;
+    let count = widths.len();
+    // no more columns than could fit even if every name were one character long
+    let max_cols = match line_width {
+        0 => count,
+        _ => count.min(line_width / MIN_COLUMN_WIDTH).max(1),
+    };
+
+    for cols in (1..=max_cols).rev() {
+        let rows = count.div_ceil(cols);
+        let mut column_widths = vec![MIN_COLUMN_WIDTH; cols];
+        for (index, width) in widths.iter().enumerate() {
+            let col = if across { index % cols } else { index / rows };
+            let separator = if col + 1 == cols { 0 } else { SEPARATOR };
+            column_widths[col] = column_widths[col].max(width + separator);
+        }
+
+        let total: usize = column_widths.iter().sum();
+        if line_width == 0 || total < line_width || cols == 1 {
+            return (rows, column_widths);
+        }
+    }
+
+    (count, vec![widths.iter().copied().max().unwrap_or(0)])
+}
+
+fn parse_width(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("ls: invalid line width: '{}'", value))
+}
+
+// COLUMNS wins over the size the terminal reports, GNU-style
+fn terminal_width() -> usize {
+    if let Some(width) = env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .filter(|width| *width > 0)
+    {
+        return width;
+    }
+
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if ok && size.ws_col > 0 {
+        return size.ws_col as usize;
+    }
+
+    80
+}
+
+// utitlies :
+fn child_path(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn entity_metadata(path: &PathBuf, follow: bool) -> Result<Metadata, Error> {
+    if follow && let Ok(metadata) = fs::metadata(path) {
+        return Ok(metadata);
+    }
+    fs::symlink_metadata(path)
+}
+
+fn format_size(bytes: u64, flags: &Flags) -> String {
+    match flags.human {
+        Some(base) => human_size(bytes, base),
+        None => bytes.to_string(),
+    }
+}
+
+// allocated size, counted in 1K blocks unless a human-readable unit was asked for
+fn format_blocks(blocks: u64, flags: &Flags) -> String {
+    match flags.human {
+        Some(base) => human_size(blocks * 1024, base),
+        None => blocks.to_string(),
+    }
+}
+
+fn get_time((secs, nsecs): (i64, i64), time_style: &TimeStyle, clock: &Clock) -> String {
+    use std::fmt::Write;
+
+    let dt = DateTime::<Utc>::from_timestamp(secs, nsecs as u32).unwrap_or_default();
+    let datetime = clock.local(dt);
+    let current_date_time = clock.now;
+
+    let six_months_ago = current_date_time - Duration::days(183);
+    let is_recent = datetime > six_months_ago && datetime < current_date_time;
+
+    let format = match time_style {
+        TimeStyle::Locale if is_recent => "%b %e %H:%M",
+        TimeStyle::Locale => "%b %e  %Y",
+        TimeStyle::FullIso => "%Y-%m-%d %H:%M:%S%.9f %z",
+        TimeStyle::LongIso => "%Y-%m-%d %H:%M",
+        TimeStyle::Iso if is_recent => "%m-%d %H:%M",
+        TimeStyle::Iso => "%Y-%m-%d ",
+        TimeStyle::Format(_, recent) if is_recent => recent,
+        TimeStyle::Format(old, _) => old,
+    };
+
+    let mut time = String::new();
+    if write!(time, "{}", datetime.format(format)).is_err() {
+        return format.to_string();
+    }
+    time
+}
+
+fn compare_entities(a: &Entity, b: &Entity, a_name: &str, b_name: &str, flags: &Flags) -> Ordering {
+    let by_name = || name_key(a_name).cmp(&name_key(b_name));
+
+    // directories stay ahead of files even with -r, and -U turns the grouping off
+    if flags.group_dirs_first && flags.sort != Sort::None {
+        let a_dir = a.file_type == EntityType::Dir;
+        let b_dir = b.file_type == EntityType::Dir;
+        if a_dir != b_dir {
+            return b_dir.cmp(&a_dir);
+        }
+    }
+
+    let ordering = match flags.sort {
+        Sort::None => return Ordering::Equal,
+        Sort::Name => by_name(),
+        Sort::Time => {
+            let field = flags.time_field;
+            b.time(field).cmp(&a.time(field)).then_with(by_name)
+        }
+        Sort::Size => b.size.cmp(&a.size).then_with(by_name),
+        Sort::Extension => extension(a_name).cmp(extension(b_name)).then_with(by_name),
+        Sort::Version => version_cmp(a_name, b_name).then_with(|| a_name.cmp(b_name)),
+    };
+
+    if flags.reverse {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+// shell-style matching for -I, --hide and .gitignore patterns, with fnmatch(3) flags
+fn fnmatch(pattern: &str, name: &OsStr, flags: libc::c_int) -> bool {
+    let (Ok(pattern), Ok(name)) = (CString::new(pattern), CString::new(name.as_bytes())) else {
+        return false;
+    };
+    unsafe { libc::fnmatch(pattern.as_ptr(), name.as_ptr(), flags) == 0 }
+}
+
+// expands an unambiguous prefix to the full option name, taking errors from the option as typed
+fn resolve_long_option(name: &str, arg: &str) -> Result<&'static str, String> {
+    if let Some(exact) = LONG_OPTIONS.iter().find(|option| **option == name) {
+        return Ok(exact);
+    }
+
+    let matches: Vec<&'static str> = LONG_OPTIONS
+        .into_iter()
+        .filter(|option| option.starts_with(name))
+        .collect();
+    match matches.as_slice() {
+        [option] | [option @ "color", "colour"] => Ok(option),
+        [] => Err(format!("ls: unrecognized option '--{}'", arg)),
+        _ => {
+            let possibilities: Vec<String> = matches
+                .iter()
+                .map(|option| format!("'--{}'", option))
+                .collect();
+            Err(format!(
+                "ls: option '--{}' is ambiguous; possibilities: {}",
+                name,
+                possibilities.join(" ")
+            ))
+        }
+    }
+}
+
+fn name_key(name: &str) -> String {
+    name.strip_prefix(".").unwrap_or(name).to_ascii_lowercase()
+}
+
+fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(index) => &name[index + 1..],
+        None => "",
+    }
+}
+
+// natural ordering of names containing version numbers, after dpkg's verrevcmp
+fn version_cmp(a: &str, b: &str) -> Ordering {
+    fn order(ch: Option<u8>) -> i32 {
+        match ch {
+            None => 0,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => c as i32,
+            Some(b'~') => -1,
+            Some(c) => c as i32 + 256,
+        }
+    }
+
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    let is_digit = |s: &[u8], k: usize| s.get(k).is_some_and(|c| c.is_ascii_digit());
+
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let (ac, bc) = (order(a.get(i).copied()), order(b.get(j).copied()));
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(a, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, j) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+
+    Ordering::Equal
+}
+
+fn get_file_type_symbols(file_type: EntityType) -> (&'static str, &'static str) {
+    match file_type {
+        EntityType::File => ("-", ""),
+        EntityType::Dir => ("d", "/"),
+        EntityType::SymLink => ("l", "@"),
+        EntityType::CharacterDevice => ("c", ""),
+        EntityType::BlockDevice => ("b", ""),
+        EntityType::Fifo => ("p", "|"),
+        EntityType::Socket => ("s", "="),
+        EntityType::Executable => ("-", "*"),
+        EntityType::None => ("", ""),
+    }
+}
+
+fn get_permissions(mode: u32) -> String {
+    let mut permissions = String::new();
+
+    let owner = (mode >> 6) & 0o7;
+    let group = (mode >> 3) & 0o7;
+    let other = mode & 0o7;
+
+    let setuid = mode & 0o4000 != 0;
+    let setgid = mode & 0o2000 != 0;
+    let sticky_bit = mode & 0o1000 != 0;
+
+    permissions.push(if owner & 0o4 != 0 { 'r' } else { '-' });
+    permissions.push(if owner & 0o2 != 0 { 'w' } else { '-' });
+    permissions.push(if setuid {
+        if owner & 0o1 != 0 { 's' } else { 'S' }
+    } else {
+        if owner & 0o1 != 0 { 'x' } else { '-' }
+    });
+
+    permissions.push(if group & 0o4 != 0 { 'r' } else { '-' });
+    permissions.push(if group & 0o2 != 0 { 'w' } else { '-' });
+    permissions.push(if setgid {
+        if group & 0o1 != 0 { 's' } else { 'S' }
+    } else {
+        if group & 0o1 != 0 { 'x' } else { '-' }
+    });
+
+    permissions.push(if other & 0o4 != 0 { 'r' } else { '-' });
+    permissions.push(if other & 0o2 != 0 { 'w' } else { '-' });
+    permissions.push(if sticky_bit {
+        if other & 0o1 != 0 { 't' } else { 'T' }
+    } else {
+        if other & 0o1 != 0 { 'x' } else { '-' }
+    });
+
+    permissions
+}
+
+fn read_link(path: &Path, file_type: &EntityType) -> Option<PathBuf> {
+    if *file_type != EntityType::SymLink {
+        return None;
+    }
+
+    fs::read_link(path).ok()
+}
+
+fn get_file_type(mode: u32) -> EntityType {
+    let mut file_type = match mode & 0o170000 {
+        0o100000 => EntityType::File,
+        0o040000 => EntityType::Dir,
+        0o120000 => EntityType::SymLink,
+        0o020000 => EntityType::CharacterDevice,
+        0o060000 => EntityType::BlockDevice,
+        0o010000 => EntityType::Fifo,
+        0o140000 => EntityType::Socket,
+        _ => EntityType::None,
+    };
+
+    let is_executable = mode & 0o111 != 0;
+    if file_type == EntityType::File && is_executable {
+        file_type = EntityType::Executable
+    };
+    file_type
+}
+
+// readdir only knows the broad type, so executables are told apart once the entry is stat'ed
+fn get_dirent_type(file_type: fs::FileType) -> EntityType {
+    if file_type.is_dir() {
+        EntityType::Dir
+    } else if file_type.is_symlink() {
+        EntityType::SymLink
+    } else if file_type.is_char_device() {
+        EntityType::CharacterDevice
+    } else if file_type.is_block_device() {
+        EntityType::BlockDevice
+    } else if file_type.is_fifo() {
+        EntityType::Fifo
+    } else if file_type.is_socket() {
+        EntityType::Socket
+    } else {
+        EntityType::File
+    }
+}
+
+fn get_parent(path: PathBuf) -> PathBuf {
+    match path.parent() {
+        Some(parent) => parent.to_path_buf(),
+        None => path,
+    }
+}
+
+fn handle_ls_erros(err: Error, entry: String) {
+    eprintln!("ls: cannot access '{}': {}", entry, error_message(&err));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_cmp_orders_like_verrevcmp() {
+        let cases = [
+            ("a2", "a10", Ordering::Less),
+            ("1.2", "1.10", Ordering::Less),
+            ("1.0~rc1", "1.0", Ordering::Less),
+            ("1.0~rc1", "1.0~rc2", Ordering::Less),
+            ("1.0", "1.0a", Ordering::Less),
+            ("1.0a", "1.0.1", Ordering::Less),
+            ("a-b", "a.b", Ordering::Less),
+            ("file9.txt", "file10.txt", Ordering::Less),
+            ("a01", "a1", Ordering::Equal),
+            ("", "", Ordering::Equal),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(version_cmp(a, b), expected, "{} vs {}", a, b);
+            assert_eq!(version_cmp(b, a), expected.reverse(), "{} vs {}", b, a);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use super::{Entity, EntityType, List, LsConfig, Target, fnmatch};
+
+// what a walk has seen so far: the totals, and the directories on the current path
+#[derive(Debug, Default)]
+struct Walk {
+    counts: Counts,
+    ancestors: HashSet<(u64, u64)>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    dirs: usize,
+    files: usize,
+    dirs_only: bool,
+}
+
+// the summary line `tree` ends with
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dirs = if self.dirs == 1 {
+            "directory"
+        } else {
+            "directories"
+        };
+        write!(f, "{} {}", self.dirs, dirs)?;
+        if !self.dirs_only {
+            let files = if self.files == 1 { "file" } else { "files" };
+            write!(f, ", {} {}", self.files, files)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_depth(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(depth) if depth > 0 => Ok(depth),
+        _ => Err(format!(
+            "ls: invalid tree depth '{}': must be greater than 0",
+            value
+        )),
+    }
+}
+
+impl LsConfig {
+    // draws each operand as a tree like `tree`, with -d keeping only directories
+    pub(super) fn print_tree(&self, out: &mut impl Write, targets: Vec<Target>) -> io::Result<()> {
+        let mut walk = Walk::default();
+        walk.counts.dirs_only = self.flags.directory;
+
+        for Target(_, mut root) in targets {
+            self.decorate(&mut root);
+            root.is_long = true;
+            writeln!(out, "{}", root)?;
+            if root.file_type != EntityType::Dir {
+                walk.counts.files += 1;
+                continue;
+            }
+
+            walk.ancestors = HashSet::from([(root.dev, root.ino)]);
+            self.print_branch(out, &mut root, "", 1, &mut walk, &[])?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "{}", walk.counts)
+    }
+
+    fn print_branch(
+        &self,
+        out: &mut impl Write,
+        dir: &mut Entity,
+        indent: &str,
+        depth: usize,
+        walk: &mut Walk,
+        ignores: &[GitIgnore],
+    ) -> io::Result<()> {
+        out.flush()?;
+        let mut list = List::new(dir.path.display().to_string());
+        if !list.get_items(dir, &self.flags) {
+            return Ok(());
+        }
+
+        let mut ignores = ignores.to_vec();
+        if self.flags.gitignore
+            && let Some(gitignore) = GitIgnore::load(&dir.path)
+        {
+            ignores.push(gitignore);
+        }
+
+        list.items.retain(|item| {
+            let is_dir = item.file_type == EntityType::Dir;
+            item.name != "."
+                && item.name != ".."
+                && (is_dir || !self.flags.directory)
+                && !is_ignored(&ignores, &item.path, is_dir)
+        });
+
+        let len = list.items.len();
+        for (index, item) in list.items.iter_mut().enumerate() {
+            let is_last = index + 1 == len;
+            let connector = if is_last { "└── " } else { "├── " };
+            self.decorate(item);
+            // like `tree`, symlinks always show where they point
+            item.is_long = true;
+
+            if item.file_type != EntityType::Dir {
+                walk.counts.files += 1;
+                writeln!(out, "{}{}{}", indent, connector, item)?;
+                continue;
+            }
+
+            walk.counts.dirs += 1;
+            // only a directory containing itself is a loop; the same one reached twice is not
+            let key = (item.dev, item.ino);
+            if walk.ancestors.contains(&key) {
+                writeln!(
+                    out,
+                    "{}{}{}  [recursive, not followed]",
+                    indent, connector, item
+                )?;
+                continue;
+            }
+            writeln!(out, "{}{}{}", indent, connector, item)?;
+
+            if self.flags.tree_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            let indent = format!("{}{}", indent, if is_last { "    " } else { "│   " });
+            walk.ancestors.insert(key);
+            self.print_branch(out, item, &indent, depth + 1, walk, &ignores)?;
+            walk.ancestors.remove(&key);
+        }
+        Ok(())
+    }
+}
+
+// the rules of one .gitignore, which apply below the directory holding it
+#[derive(Debug, Clone)]
+struct GitIgnore {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    // a pattern with a slash is matched against the path from `base`, otherwise the name alone
+    anchored: bool,
+}
+
+impl GitIgnore {
+    fn load(dir: &Path) -> Option<Self> {
+        let text = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        Some(Self::parse(dir, &text))
+    }
+
+    fn parse(base: &Path, text: &str) -> Self {
+        let mut rules = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            let line = line.strip_prefix("**/").unwrap_or(line);
+            let anchored = line.contains('/');
+            let mut pattern = line.strip_prefix('/').unwrap_or(line).to_string();
+            // `dir/**` hides what is inside, which is the first level below it
+            if let Some(dir) = pattern.strip_suffix("/**") {
+                pattern = format!("{}/*", dir);
+            }
+
+            if !pattern.is_empty() {
+                rules.push(Rule {
+                    pattern,
+                    negated,
+                    dir_only,
+                    anchored,
+                });
+            }
+        }
+
+        Self {
+            base: base.to_path_buf(),
+            rules,
+        }
+    }
+
+    // the verdict of the last matching rule, if any
+    fn matches(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let name = path.file_name()?;
+
+        self.rules.iter().rev().find_map(|rule| {
+            if rule.dir_only && !is_dir {
+                return None;
+            }
+
+            let matched = if rule.anchored {
+                matches_path(&rule.pattern, relative.as_os_str())
+            } else {
+                fnmatch(&rule.pattern, name, 0)
+            };
+            matched.then_some(!rule.negated)
+        })
+    }
+}
+
+// `**` may cross directories and a lone `*` may not; `/**/` also stands for no directory at
+// all, so each one is tried collapsed to `/` as well
+fn matches_path(pattern: &str, path: &OsStr) -> bool {
+    let flags = if pattern.contains("**") {
+        0
+    } else {
+        libc::FNM_PATHNAME
+    };
+    fnmatch(pattern, path, flags)
+        || pattern.match_indices("/**/").any(|(index, _)| {
+            let collapsed = format!("{}/{}", &pattern[..index], &pattern[index + 4..]);
+            matches_path(&collapsed, path)
+        })
+}
+
+// deeper .gitignore files take precedence over the ones above them
+fn is_ignored(ignores: &[GitIgnore], path: &Path, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|gitignore| gitignore.matches(path, is_dir))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitignore_rules() {
+        let gitignore = GitIgnore::parse(
+            Path::new("/repo"),
+            "# comment\n/build\n*.o\n!keep.o\ndir/\na/**/b\n",
+        );
+        let cases = [
+            ("build", true, Some(true)),
+            ("src/build", true, None),
+            ("x.o", false, Some(true)),
+            ("src/x.o", false, Some(true)),
+            ("keep.o", false, Some(false)),
+            ("dir", true, Some(true)),
+            ("dir", false, None),
+            ("src/dir", true, Some(true)),
+            ("a/b", false, Some(true)),
+            ("a/x/b", false, Some(true)),
+            ("a/x/y/b", false, Some(true)),
+            ("c/a/b", false, None),
+            ("a/bc", false, None),
+            ("comment", false, None),
+        ];
+        for (path, is_dir, expected) in cases {
+            let path = Path::new("/repo").join(path);
+            assert_eq!(gitignore.matches(&path, is_dir), expected, "{:?}", path);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::{cell::RefCell, collections::HashSet, io, path::Path};
+
+use super::{Entity, EntityType};
+
+const ACL_ACCESS: &str = "system.posix_acl_access";
+const ACL_DEFAULT: &str = "system.posix_acl_default";
+const SELINUX: &str = "security.selinux";
+
+// reads the extended attributes behind the `+` and `.` mode markers, -Z and -@,
+// remembering filesystems that don't support them so they are only asked once
+#[derive(Debug, Default, Clone)]
+pub struct Xattrs {
+    // with -L the attributes come from what a symlink points to
+    pub follow: bool,
+    unsupported_devs: RefCell<HashSet<u64>>,
+}
+
+impl Xattrs {
+    // an access ACL, or on a directory a default ACL for new entries
+    pub fn has_acl(&self, entity: &Entity) -> bool {
+        if entity.file_type == EntityType::SymLink {
+            return false;
+        }
+
+        self.get(entity, ACL_ACCESS).is_some()
+            || (entity.file_type == EntityType::Dir && self.get(entity, ACL_DEFAULT).is_some())
+    }
+
+    pub fn context(&self, entity: &Entity) -> Option<String> {
+        let value = self.get(entity, SELINUX)?;
+        let value = value.strip_suffix(b"\0").unwrap_or(&value);
+        Some(String::from_utf8_lossy(value).into_owned())
+    }
+
+    // every attribute name with the size of its value, sorted by name
+    pub fn list(&self, entity: &Entity) -> Vec<(String, usize)> {
+        if self.unsupported_devs.borrow().contains(&entity.dev) {
+            return Vec::new();
+        }
+
+        let names = if self.follow {
+            xattr::list_deref(&entity.path)
+        } else {
+            xattr::list(&entity.path)
+        };
+        let names = match names {
+            Ok(names) => names,
+            Err(err) => {
+                self.check_support(entity, &err);
+                return Vec::new();
+            }
+        };
+
+        let mut attributes: Vec<(String, usize)> = names
+            .filter_map(|name| {
+                let size = self.read(&entity.path, &name).ok()??.len();
+                Some((name.to_string_lossy().into_owned(), size))
+            })
+            .collect();
+        attributes.sort();
+        attributes
+    }
+
+    fn get(&self, entity: &Entity, name: &str) -> Option<Vec<u8>> {
+        if self.unsupported_devs.borrow().contains(&entity.dev) {
+            return None;
+        }
+
+        match self.read(&entity.path, name) {
+            Ok(value) => value,
+            Err(err) => {
+                self.check_support(entity, &err);
+                None
+            }
+        }
+    }
+
+    fn read(&self, path: &Path, name: impl AsRef<std::ffi::OsStr>) -> io::Result<Option<Vec<u8>>> {
+        if self.follow {
+            xattr::get_deref(path, name)
+        } else {
+            xattr::get(path, name)
+        }
+    }
+
+    fn check_support(&self, entity: &Entity, err: &io::Error) {
+        if err.raw_os_error() == Some(libc::EOPNOTSUPP) {
+            self.unsupported_devs.borrow_mut().insert(entity.dev);
+        }
+    }
+}
@@ -6,6 +6,8 @@ pub mod exit;
 pub mod ls;
 pub mod mkdir;
 pub mod mv;
+mod overwrite;
+mod progress;
 pub mod pwd;
 pub mod rm;
 pub mod clear;
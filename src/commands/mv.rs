@@ -1,147 +1,438 @@
-use crate::utils::direct_children;
-use std::{fs, io::Error, path::Path};
+use super::{
+    cp::{Cp, is_inside},
+    overwrite::{Overwrite, Policy},
+    progress::Report,
+};
+use crate::utils::error_message;
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub struct Mv {
+    policy: Policy,
+    report: Report,
+    // -t: every operand is a source moved into this directory
+    target_directory: Option<String>,
+    // -T: the last operand is replaced, never moved into
+    no_target_directory: bool,
+    strip_trailing_slashes: bool,
+    // --exchange: the two operands swap places in one step
+    exchange: bool,
+    // --no-copy: a move across filesystems fails instead of copying
+    no_copy: bool,
+    // --no-replace: an existing destination is an error, even one created at the last moment
+    no_replace: bool,
+    pub sources: Vec<String>,
+    pub target: String,
+}
+
 pub fn mv_handler(args: Vec<String>) {
-    if args.is_empty() {
-        eprintln!("mv: missing file operand");
-        return;
+    match Mv::new(args) {
+        Ok(mv) => mv.execute(),
+        Err(e) => eprintln!("{}", e),
     }
-    if args.len() == 1 {
-        println!("mv: missing destination file operand after '{}'", args[0]);
-        return;
-    }
-    let destination = Path::new(&args[args.len() - 1]);
-    if !destination.exists() && args.len() == 2 {
-        let src = Path::new(&args[0]);
-        if !src.exists() {
-            eprintln!("mv: cannot stat '{:?}': No such file or directory", src);
-            return;
-        }
-        if let Err(e) = fs::rename(src, destination) {
-            eprintln!("mv: {e}");
-            return;
-        }
+}
 
-        return;
-    } else if !destination.exists() && args.len() > 2 {
-        eprintln!("mv: target '{}' is not a directory", &args[args.len() - 1]);
-        return;
-    }
+impl Mv {
+    pub fn new(args: Vec<String>) -> Result<Self, String> {
+        let mut mv = Self {
+            policy: Policy::new("mv"),
+            report: Report::new("mv"),
+            target_directory: None,
+            no_target_directory: false,
+            strip_trailing_slashes: false,
+            exchange: false,
+            no_copy: false,
+            no_replace: false,
+            sources: Vec::new(),
+            target: String::new(),
+        };
+        let mut operands = Vec::new();
+        let mut only_operands = false;
+        let mut args = args.into_iter();
 
-    if destination.is_dir() {
-        for opt in args[..args.len() - 1].iter() {
-            if opt == "." || opt == ".." {
-                eprintln!(
-                    "mv: cannot move '{opt}' to {:?}: Device or resource busy",
-                    destination
-                );
-                continue;
-            }
-            let src: &Path = Path::new(&opt);
-            if !src.exists() {
-                eprintln!("mv: cannot stat '{opt}': No such file or directory");
-                continue;
+        while let Some(arg) = args.next() {
+            if only_operands || arg == "-" || !arg.starts_with('-') {
+                operands.push(arg);
+            } else if arg == "--" {
+                only_operands = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                mv.parse_long_flag(long, &mut args)?;
+            } else {
+                mv.parse_flags(&arg, &mut args)?;
             }
-            let src_real = fs::canonicalize(src).unwrap();
-            let mut dest_real = fs::canonicalize(destination).unwrap();
+        }
 
-            if dest_real.is_dir() {
-                dest_real = dest_real.join(src.file_name().unwrap());
-            }
+        if mv.exchange && mv.target_directory.is_some() {
+            return Err("mv: cannot combine --exchange and --target-directory (-t)".to_string());
+        }
+        if mv.target_directory.is_some() && mv.no_target_directory {
+            return Err(
+                "mv: cannot combine --target-directory (-t) and --no-target-directory (-T)"
+                    .to_string(),
+            );
+        }
 
-            if src_real == dest_real {
-                eprintln!("cp: '{opt}' and '{:?}' are the same file", dest_real);
-                continue;
+        if let Some(dir) = &mv.target_directory {
+            if operands.is_empty() {
+                return Err("mv: missing file operand".to_string());
             }
-            if src.is_file() {
-                if let Some(file_name) = src.file_name() {
-                    let new_dest = destination.join(file_name);
-
-                    if let Err(e) = fs::rename(&src, new_dest) {
-                        eprintln!("mv: {e}");
-                        continue;
-                    }
-                } else {
-                    eprintln!(
-                        "mv: cannot join {:?} with {:?}",
-                        destination,
-                        src.file_name()
-                    );
-                    continue;
+            mv.target = dir.clone();
+        } else {
+            mv.target = match operands.len() {
+                0 => return Err("mv: missing file operand".to_string()),
+                1 => {
+                    return Err(format!(
+                        "mv: missing destination file operand after '{}'",
+                        operands[0]
+                    ));
                 }
-            } else if src.is_dir() {
-                if let Err(e) = move_dir_recursivly(src, destination) {
-                    eprintln!("mv: {e}");
-                    continue;
+                3.. if mv.no_target_directory || mv.exchange => {
+                    return Err(format!("mv: extra operand '{}'", operands[2]));
                 }
-            } else {
-                if let Some(file_name) = src.file_name() {
-                    let new_dest = destination.join(file_name);
+                _ => operands.pop().unwrap_or_default(),
+            };
+        }
 
-                    if let Err(e) = fs::rename(&src, new_dest) {
-                        eprintln!("mv: {e}");
-                        continue;
+        if mv.strip_trailing_slashes {
+            for operand in &mut operands {
+                let stripped = operand.trim_end_matches('/');
+                *operand = if stripped.is_empty() { "/" } else { stripped }.to_string();
+            }
+        }
+        mv.sources = operands;
+        Ok(mv)
+    }
+
+    fn parse_flags(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        for (index, ch) in arg.char_indices().skip(1) {
+            match ch {
+                'S' | 't' => {
+                    let value = &arg[index + 1..];
+                    let value = if value.is_empty() {
+                        rest.next()
+                    } else {
+                        Some(value.to_string())
+                    };
+                    let Some(value) = value else {
+                        return Err(format!("mv: option requires an argument -- '{}'", ch));
+                    };
+                    if ch == 'S' {
+                        self.policy.set_suffix(&value);
+                    } else {
+                        self.target_directory = Some(value);
                     }
-                } else {
-                    eprintln!(
-                        "mv: cannot join {:?} with {:?}",
-                        destination,
-                        src.file_name()
-                    );
-                    continue;
+                    break;
                 }
+                'T' => self.no_target_directory = true,
+                'v' => self.report.verbose = true,
+                _ if self.policy.short_flag(ch)? => {}
+                _ => return Err(format!("mv: invalid option -- '{}'", ch)),
             }
         }
-    } else if destination.is_file() {
-        if args.len() > 2 {
-            eprintln!("mv: target '{:?}' is not a directory", destination);
-            return;
-        } else if args.len() == 2 {
-            let src_meta = fs::metadata(&args[0]);
-            if let Ok(file) = src_meta {
-                if !file.is_file() {
-                    eprintln!(
-                        "mv: cannot overwrite non-directory {:?} with directory '{}'",
-                        destination, args[0]
-                    );
-                    return;
-                }
+        Ok(())
+    }
+
+    fn parse_long_flag(
+        &mut self,
+        arg: &str,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        let (name, mut value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg, None),
+        };
+        if (name == "suffix" || name == "target-directory") && value.is_none() {
+            value = rest.next();
+        }
+        if self.policy.long_flag(name, value.as_deref())?
+            || (value.is_none() && self.report.long_flag(name))
+        {
+            return Ok(());
+        }
+
+        match (name, value) {
+            ("target-directory", Some(dir)) => self.target_directory = Some(dir),
+            ("target-directory", None) => {
+                return Err("mv: option '--target-directory' requires an argument".to_string());
             }
-            let src_path = Path::new(&args[0]);
-            if src_path.file_name() == destination.file_name() {
+            ("no-target-directory", None) => self.no_target_directory = true,
+            ("strip-trailing-slashes", None) => self.strip_trailing_slashes = true,
+            ("exchange", None) => self.exchange = true,
+            ("no-copy", None) => self.no_copy = true,
+            ("no-replace", None) => self.no_replace = true,
+            _ => return Err(format!("mv: unrecognized option '--{}'", arg)),
+        }
+        Ok(())
+    }
+
+    fn execute(&self) {
+        let target = Path::new(&self.target);
+        // exchanging a directory swaps the directory itself, as with -T
+        let into_dir = match fs::metadata(target) {
+            Ok(meta) => meta.is_dir() && !self.no_target_directory && !self.exchange,
+            Err(e) if self.target_directory.is_some() => {
                 eprintln!(
-                    "mv: {:?} and {:?} are the same file",
-                    src_path.file_name().unwrap(),
-                    destination.file_name().unwrap()
+                    "mv: failed to access '{}': {}",
+                    self.target,
+                    error_message(&e)
                 );
                 return;
             }
-            if let Err(e) = fs::rename(src_path, destination) {
-                eprintln!("mv: {e}");
-                return;
+            Err(_) => false,
+        };
+
+        if !into_dir && self.target_directory.is_some() {
+            eprintln!("mv: target directory '{}': Not a directory", self.target);
+            return;
+        }
+        if !into_dir && self.sources.len() > 1 {
+            eprintln!("mv: target '{}' is not a directory", self.target);
+            return;
+        }
+
+        let sources: Vec<&Path> = self.sources.iter().map(Path::new).collect();
+        self.report.start(&sources, false);
+
+        for src in sources {
+            // an existing directory receives each source under its last component
+            let dest = match src.components().next_back() {
+                Some(name) if into_dir => target.join(name),
+                _ => target.to_path_buf(),
+            };
+
+            if let Err(e) = self.move_one(src, &dest) {
+                eprintln!("{}", e);
             }
         }
+        self.report.finish();
     }
-}
 
-pub fn move_dir_recursivly(src: &Path, dest: &Path) -> Result<(), Error> {
-    let new_dest = dest.join(src.file_name().unwrap());
-    if src.is_file() {
-        if let Err(e) = fs::rename(src, new_dest) {
-            return Err(e);
+    fn move_one(&self, src: &Path, dest: &Path) -> Result<(), String> {
+        let src_meta = fs::symlink_metadata(src)
+            .map_err(|e| format!("mv: cannot stat '{}': {}", src.display(), error_message(&e)))?;
+        let dest_meta = fs::symlink_metadata(dest).ok();
+
+        // the same file under two names, not merely two files with the same name
+        if let Some(dest_meta) = &dest_meta
+            && dest_meta.dev() == src_meta.dev()
+            && dest_meta.ino() == src_meta.ino()
+        {
+            return Err(format!(
+                "mv: '{}' and '{}' are the same file",
+                src.display(),
+                dest.display()
+            ));
+        }
+
+        if self.exchange {
+            return self.exchange_paths(src, dest);
+        }
+        if self.no_replace && dest_meta.is_some() {
+            return Err(format!(
+                "mv: cannot move '{}' to '{}': {}",
+                src.display(),
+                dest.display(),
+                error_message(&io::Error::from_raw_os_error(libc::EEXIST))
+            ));
         }
-    } else if src.is_dir() {
-        if let Err(e) = fs::create_dir_all(&new_dest) {
-            return Err(e);
+
+        if src_meta.is_dir() && is_inside(src, dest) {
+            return Err(format!(
+                "mv: cannot move '{}' to a subdirectory of itself, '{}'",
+                src.display(),
+                dest.display()
+            ));
         }
-        for child in direct_children(src) {
-            if let Err(e) = move_dir_recursivly(&child, &new_dest) {
-                return Err(e);
+        if let Some(dest_meta) = &dest_meta {
+            if dest_meta.is_dir() && !src_meta.is_dir() {
+                return Err(format!(
+                    "mv: cannot overwrite directory '{}' with non-directory",
+                    dest.display()
+                ));
+            }
+            if !dest_meta.is_dir() && src_meta.is_dir() {
+                return Err(format!(
+                    "mv: cannot overwrite non-directory '{}' with directory '{}'",
+                    dest.display(),
+                    src.display()
+                ));
             }
         }
-        if let Err(e) = fs::remove_dir_all(&src) {
-            return Err(e);
+
+        if !self.policy.allows(&src_meta, dest)? {
+            return Ok(());
         }
+        self.move_path(src, dest)
     }
 
+    // renames in one step where it can; across filesystems the source is copied with all
+    // its attributes and then removed
+    fn move_path(&self, src: &Path, dest: &Path) -> Result<(), String> {
+        let no_clobber = self.policy.clobber == Overwrite::NoClobber;
+        let err = match self.put_in_place(src, dest) {
+            Ok(()) => {
+                self.moved(src, dest);
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+        if no_clobber && !self.no_replace && err.raw_os_error() == Some(libc::EEXIST) {
+            return Ok(());
+        }
+        if err.raw_os_error() != Some(libc::EXDEV) || self.no_copy {
+            return Err(format!(
+                "mv: cannot move '{}' to '{}': {}",
+                src.display(),
+                dest.display(),
+                error_message(&err)
+            ));
+        }
+
+        self.copy_across(src, dest)
+    }
+
+    // -n and --no-replace also let the kernel refuse, in case the destination appeared since
+    // it was checked; where the filesystem cannot, -n settles for checking first
+    fn put_in_place(&self, src: &Path, dest: &Path) -> io::Result<()> {
+        let no_clobber = self.policy.clobber == Overwrite::NoClobber;
+        if !self.no_replace && !no_clobber {
+            return rename(src, dest, 0);
+        }
+
+        match rename(src, dest, libc::RENAME_NOREPLACE) {
+            Err(err)
+                if !self.no_replace
+                    && matches!(err.raw_os_error(), Some(libc::EINVAL | libc::ENOSYS)) =>
+            {
+                if fs::symlink_metadata(dest).is_ok() {
+                    return Err(io::Error::from_raw_os_error(libc::EEXIST));
+                }
+                rename(src, dest, 0)
+            }
+            result => result,
+        }
+    }
+
+    // the copy is made under a name next to `dest` and renamed over it only once complete,
+    // so a copy that fails partway leaves the old destination as it was, and -n and
+    // --no-replace still refuse a destination created while copying
+    fn copy_across(&self, src: &Path, dest: &Path) -> Result<(), String> {
+        let cannot_move = |err: io::Error| {
+            format!(
+                "mv: cannot move '{}' to '{}': {}",
+                src.display(),
+                dest.display(),
+                error_message(&err)
+            )
+        };
+        // as rename would, a directory is only replaced when it is empty
+        if is_non_empty_dir(dest) {
+            return Err(cannot_move(io::Error::from_raw_os_error(libc::ENOTEMPTY)));
+        }
+
+        let temp = temp_sibling(dest);
+        if !Cp::archive("mv").copy_all(src, &temp) {
+            let _ = remove_path(&temp);
+            return Ok(());
+        }
+        if let Err(err) = self.put_in_place(&temp, dest) {
+            let _ = remove_path(&temp);
+            if self.policy.clobber == Overwrite::NoClobber
+                && !self.no_replace
+                && err.raw_os_error() == Some(libc::EEXIST)
+            {
+                return Ok(());
+            }
+            return Err(cannot_move(err));
+        }
+
+        remove_path(src).map_err(|e| {
+            format!(
+                "mv: cannot remove '{}': {}",
+                src.display(),
+                error_message(&e)
+            )
+        })?;
+        self.moved(src, dest);
+        Ok(())
+    }
+
+    fn exchange_paths(&self, src: &Path, dest: &Path) -> Result<(), String> {
+        rename(src, dest, libc::RENAME_EXCHANGE).map_err(|e| {
+            format!(
+                "mv: cannot exchange '{}' and '{}': {}",
+                src.display(),
+                dest.display(),
+                error_message(&e)
+            )
+        })?;
+        self.report.exchanged(src, dest);
+        Ok(())
+    }
+
+    fn moved(&self, src: &Path, dest: &Path) {
+        self.report.tree_done(dest);
+        self.report.done(src, dest);
+    }
+}
+
+// renameat2, for the flags fs::rename has no way to pass
+fn rename(src: &Path, dest: &Path, flags: libc::c_uint) -> io::Result<()> {
+    if flags == 0 {
+        return fs::rename(src, dest);
+    }
+
+    let src = CString::new(src.as_os_str().as_bytes())?;
+    let dest = CString::new(dest.as_os_str().as_bytes())?;
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            src.as_ptr(),
+            libc::AT_FDCWD,
+            dest.as_ptr(),
+            flags,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
     Ok(())
 }
+
+fn is_non_empty_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir())
+        && fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+// `dest.~mvtmp`, numbered if that name is taken
+fn temp_sibling(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".~mvtmp");
+    let mut temp = dest.with_file_name(&name);
+    let mut count = 1;
+    while fs::symlink_metadata(&temp).is_ok() {
+        let mut numbered = name.clone();
+        numbered.push(count.to_string());
+        temp = dest.with_file_name(numbered);
+        count += 1;
+    }
+    temp
+}
+
+// removes a file, a symlink or a whole directory tree, never following symlinks
+fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
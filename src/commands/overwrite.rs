@@ -0,0 +1,317 @@
+use std::{
+    env,
+    ffi::CString,
+    fs::{self, Metadata},
+    io::{self, IsTerminal},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use crate::utils::{confirm, error_message};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overwrite {
+    #[default]
+    Default,
+    // -f
+    Force,
+    // -i
+    Interactive,
+    // -n
+    NoClobber,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backup {
+    #[default]
+    None,
+    // `dest~`
+    Simple,
+    // `dest.~N~`
+    Numbered,
+    // numbered if the destination already has numbered backups, simple otherwise
+    Existing,
+}
+
+impl Backup {
+    fn parse(word: &str, cmd: &str) -> Result<Self, String> {
+        match word {
+            "none" | "off" => Ok(Backup::None),
+            "simple" | "never" => Ok(Backup::Simple),
+            "existing" | "nil" => Ok(Backup::Existing),
+            "numbered" | "t" => Ok(Backup::Numbered),
+            _ => Err(format!(
+                "{}: invalid argument '{}' for '--backup'\nValid arguments are:\n  - 'none', 'off'\n  - 'simple', 'never'\n  - 'existing', 'nil'\n  - 'numbered', 't'",
+                cmd, word
+            )),
+        }
+    }
+
+    // what -b and a bare --backup mean, which VERSION_CONTROL may change
+    fn from_env(cmd: &str) -> Result<Self, String> {
+        match env::var("VERSION_CONTROL") {
+            Ok(word) if !word.is_empty() => Self::parse(&word, cmd),
+            _ => Ok(Backup::Existing),
+        }
+    }
+}
+
+// what cp and mv do when the destination already exists: -i, -n, -f, -u, --backup and -S
+#[derive(Debug, Clone)]
+pub struct Policy {
+    cmd: &'static str,
+    pub clobber: Overwrite,
+    // cp -f: a destination that cannot be opened is removed and created again
+    pub force: bool,
+    pub update: bool,
+    pub backup: Backup,
+    pub suffix: String,
+}
+
+impl Policy {
+    pub fn new(cmd: &'static str) -> Self {
+        Self {
+            cmd,
+            clobber: Overwrite::Default,
+            force: false,
+            update: false,
+            backup: Backup::None,
+            suffix: env::var("SIMPLE_BACKUP_SUFFIX").unwrap_or("~".to_string()),
+        }
+    }
+
+    // the last of -i, -n and -f wins, except that cp -f only adds the retry after removal
+    pub fn short_flag(&mut self, ch: char) -> Result<bool, String> {
+        match ch {
+            'i' => self.clobber = Overwrite::Interactive,
+            'n' => self.clobber = Overwrite::NoClobber,
+            'f' => {
+                self.force = true;
+                if self.cmd == "mv" {
+                    self.clobber = Overwrite::Force;
+                }
+            }
+            'u' => self.update = true,
+            'b' => self.backup = Backup::from_env(self.cmd)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn long_flag(&mut self, name: &str, value: Option<&str>) -> Result<bool, String> {
+        match (name, value) {
+            ("interactive", None) => self.short_flag('i')?,
+            ("no-clobber", None) => self.short_flag('n')?,
+            ("force", None) => self.short_flag('f')?,
+            ("update", None) => self.short_flag('u')?,
+            ("backup", None) => self.short_flag('b')?,
+            ("backup", Some(word)) => {
+                self.backup = Backup::parse(word, self.cmd)?;
+                true
+            }
+            ("suffix", Some(suffix)) => {
+                self.set_suffix(suffix);
+                true
+            }
+            ("suffix", None) => {
+                return Err(format!(
+                    "{}: option '--suffix' requires an argument",
+                    self.cmd
+                ));
+            }
+            _ => return Ok(false),
+        };
+        Ok(true)
+    }
+
+    // -S also turns backups on, as in GNU coreutils
+    pub fn set_suffix(&mut self, suffix: &str) {
+        self.suffix = suffix.to_string();
+        if self.backup == Backup::None {
+            self.backup = Backup::Existing;
+        }
+    }
+
+    // whether `dest` may be replaced by `src`; an existing destination is backed up first
+    pub fn allows(&self, src_meta: &Metadata, dest: &Path) -> Result<bool, String> {
+        let Ok(dest_meta) = fs::symlink_metadata(dest) else {
+            return Ok(true);
+        };
+
+        if self.clobber == Overwrite::NoClobber {
+            return Ok(false);
+        }
+
+        if self.update && !dest_meta.is_dir() {
+            let newer = match (src_meta.modified(), dest_meta.modified()) {
+                (Ok(src), Ok(dest)) => src > dest,
+                _ => true,
+            };
+            if !newer {
+                return Ok(false);
+            }
+        }
+
+        let asked = match self.clobber {
+            Overwrite::Interactive => {
+                Some(format!("{}: overwrite '{}'? ", self.cmd, dest.display()))
+            }
+            // mv asks before replacing a file the user could not write to
+            Overwrite::Default if self.cmd == "mv" && is_read_only(dest, &dest_meta) => {
+                Some(format!(
+                    "{}: replace '{}', overriding mode {:04o} ({})? ",
+                    self.cmd,
+                    dest.display(),
+                    dest_meta.mode() & 0o7777,
+                    symbolic_mode(dest_meta.mode())
+                ))
+            }
+            _ => None,
+        };
+        if let Some(prompt) = asked
+            && !confirm(&prompt)
+        {
+            return Ok(false);
+        }
+
+        if self.backup != Backup::None && !dest_meta.is_dir() {
+            self.make_backup(dest)?;
+        }
+        Ok(true)
+    }
+
+    fn make_backup(&self, dest: &Path) -> Result<(), String> {
+        let backup = self.backup_path(dest);
+        fs::rename(dest, &backup).map_err(|e| {
+            format!(
+                "{}: cannot backup '{}': {}",
+                self.cmd,
+                dest.display(),
+                error_message(&e)
+            )
+        })
+    }
+
+    fn backup_path(&self, dest: &Path) -> PathBuf {
+        let simple = || {
+            let mut name = dest.as_os_str().to_os_string();
+            name.push(&self.suffix);
+            PathBuf::from(name)
+        };
+
+        let highest = match self.backup {
+            Backup::Simple | Backup::None => return simple(),
+            Backup::Numbered | Backup::Existing => highest_backup(dest),
+        };
+        if self.backup == Backup::Existing && highest == 0 {
+            return simple();
+        }
+
+        let mut name = dest.as_os_str().to_os_string();
+        name.push(format!(".~{}~", highest + 1));
+        PathBuf::from(name)
+    }
+}
+
+// the largest N among the `dest.~N~` files beside `dest`, or 0
+fn highest_backup(dest: &Path) -> u64 {
+    let Some(name) = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+    else {
+        return 0;
+    };
+    let dir = match dest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let prefix = format!("{}.~", name);
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.file_name().to_string_lossy().into_owned();
+            entry
+                .strip_prefix(&prefix)?
+                .strip_suffix('~')?
+                .parse::<u64>()
+                .ok()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// a file the user could not write to, which is worth asking about on a terminal
+pub(super) fn is_read_only(dest: &Path, meta: &Metadata) -> bool {
+    if meta.file_type().is_symlink() || !io::stdin().is_terminal() {
+        return false;
+    }
+    let Ok(path) = CString::new(dest.as_os_str().as_encoded_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) != 0 }
+}
+
+fn symbolic_mode(mode: u32) -> String {
+    let mut text = String::new();
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(backup: Backup) -> Policy {
+        let mut policy = Policy::new("cp");
+        policy.backup = backup;
+        policy.suffix = "~".to_string();
+        policy
+    }
+
+    #[test]
+    fn backup_names() {
+        let dir = env::temp_dir().join(format!("backup-names-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("dest");
+        let name = |name: &str| dir.join(name);
+
+        // (files beside dest, backup method, expected backup name)
+        let cases = [
+            (&[][..], Backup::Simple, "dest~"),
+            (&[], Backup::Numbered, "dest.~1~"),
+            (&[], Backup::Existing, "dest~"),
+            (&["dest~"], Backup::Existing, "dest~"),
+            (&["dest.~1~"], Backup::Numbered, "dest.~2~"),
+            (&["dest.~1~"], Backup::Existing, "dest.~2~"),
+            (&["dest.~1~"], Backup::Simple, "dest~"),
+            (
+                &["dest.~9~", "dest.~10~", "dest.~x~", "other.~20~"],
+                Backup::Numbered,
+                "dest.~11~",
+            ),
+        ];
+        for (existing, backup, expected) in cases {
+            for file in existing {
+                fs::write(name(file), "").unwrap();
+            }
+            assert_eq!(policy(backup).backup_path(&dest), name(expected));
+            for file in existing {
+                fs::remove_file(name(file)).unwrap();
+            }
+        }
+
+        let mut with_suffix = policy(Backup::None);
+        with_suffix.set_suffix(".bak");
+        assert_eq!(with_suffix.backup_path(&dest), name("dest.bak"));
+        fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    fmt, fs,
+    io::{self, IsTerminal, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::utils::{direct_children, human_size};
+
+// how often the status line is redrawn on a terminal, and printed again elsewhere
+const REDRAW: Duration = Duration::from_millis(100);
+const REPRINT: Duration = Duration::from_secs(2);
+
+// -v and --progress, shared by cp and mv; workers of cp -j report through it at once
+#[derive(Debug)]
+pub struct Report {
+    cmd: &'static str,
+    pub verbose: bool,
+    pub progress: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    total_bytes: u64,
+    total_files: u64,
+    bytes: u64,
+    files: u64,
+    start: Instant,
+    // when the status was last shown, if it has been
+    shown: Option<Instant>,
+    // stderr is a terminal, so the status is one line drawn over itself
+    redraw: bool,
+}
+
+impl Report {
+    pub fn new(cmd: &'static str) -> Self {
+        Self {
+            cmd,
+            verbose: false,
+            progress: false,
+            state: Mutex::new(State {
+                total_bytes: 0,
+                total_files: 0,
+                bytes: 0,
+                files: 0,
+                start: Instant::now(),
+                shown: None,
+                redraw: io::stderr().is_terminal(),
+            }),
+        }
+    }
+
+    pub fn long_flag(&mut self, name: &str) -> bool {
+        match name {
+            "verbose" => self.verbose = true,
+            "progress" => self.progress = true,
+            _ => return false,
+        }
+        true
+    }
+
+    // adds up what the operands hold before anything is copied, so there is a total;
+    // `follow` is set when symlinks among the operands are copied as what they point to
+    pub fn start(&self, sources: &[&Path], follow: bool) {
+        if !self.progress {
+            return;
+        }
+        let mut state = self.lock();
+        for source in sources {
+            let (bytes, files) = tree_size(source, follow);
+            state.total_bytes += bytes;
+            state.total_files += files;
+        }
+        state.start = Instant::now();
+    }
+
+    // `'a' -> 'b'` for cp, `renamed 'a' -> 'b'` for mv
+    pub fn done(&self, src: &Path, dest: &Path) {
+        let action = if self.cmd == "mv" { "renamed " } else { "" };
+        self.verbose_line(format!(
+            "{}'{}' -> '{}'",
+            action,
+            src.display(),
+            dest.display()
+        ));
+    }
+
+    // mv --exchange
+    pub fn exchanged(&self, src: &Path, dest: &Path) {
+        self.verbose_line(format!(
+            "exchanged '{}' <-> '{}'",
+            src.display(),
+            dest.display()
+        ));
+    }
+
+    // printed on its own line, with the status line drawn again below it
+    fn verbose_line(&self, line: String) {
+        if !self.verbose {
+            return;
+        }
+        let state = self.lock();
+        let drawn = self.progress && state.redraw && state.shown.is_some();
+        if drawn {
+            eprint!("\r\x1b[K");
+        }
+        println!("{}", line);
+        if drawn {
+            let _ = io::stdout().flush();
+            eprint!("{}", state);
+        }
+    }
+
+    pub fn advance(&self, bytes: u64) {
+        self.update(bytes, 0);
+    }
+
+    pub fn file_done(&self) {
+        self.update(0, 1);
+    }
+
+    // a whole tree that was moved at once
+    pub fn tree_done(&self, path: &Path) {
+        if self.progress {
+            let (bytes, files) = tree_size(path, false);
+            self.update(bytes, files);
+        }
+    }
+
+    pub fn finish(&self) {
+        if !self.progress {
+            return;
+        }
+        let state = self.lock();
+        if state.redraw {
+            eprintln!("\r\x1b[K{}", state);
+        } else {
+            eprintln!("{}", state);
+        }
+    }
+
+    fn update(&self, bytes: u64, files: u64) {
+        if !self.progress {
+            return;
+        }
+        let mut state = self.lock();
+        state.bytes += bytes;
+        state.files += files;
+
+        let now = Instant::now();
+        let interval = if state.redraw { REDRAW } else { REPRINT };
+        let due = match state.shown {
+            Some(shown) => now - shown >= interval,
+            None => now - state.start >= interval,
+        };
+        if !due {
+            return;
+        }
+        state.shown = Some(now);
+        if state.redraw {
+            eprint!("\r\x1b[K{}", state);
+        } else {
+            eprintln!("{}", state);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// `12M / 1.2G (1%), 3/40 files, 45M/s, ETA 0:27`
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = match self.total_bytes {
+            0 => 100,
+            total => (self.bytes.min(total) * 100 / total) as u32,
+        };
+        write!(
+            f,
+            "{} / {} ({}%), {}/{} files",
+            human_size(self.bytes, 1024),
+            human_size(self.total_bytes, 1024),
+            percent,
+            self.files,
+            self.total_files
+        )?;
+
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if elapsed <= 0.0 || self.bytes == 0 {
+            return Ok(());
+        }
+        let rate = self.bytes as f64 / elapsed;
+        write!(f, ", {}/s", human_size(rate as u64, 1024))?;
+
+        let left = self.total_bytes.saturating_sub(self.bytes) as f64 / rate;
+        let left = left.ceil() as u64;
+        write!(f, ", ETA {}:{:02}", left / 60, left % 60)
+    }
+}
+
+// the bytes of the regular files below `path` and the count of everything but directories,
+// following no symlink but `path` itself when `follow` is set
+fn tree_size(path: &Path, follow: bool) -> (u64, u64) {
+    let meta = if follow {
+        fs::metadata(path)
+    } else {
+        fs::symlink_metadata(path)
+    };
+    let Ok(meta) = meta else {
+        return (0, 0);
+    };
+    if !meta.is_dir() {
+        let bytes = if meta.is_file() { meta.len() } else { 0 };
+        return (bytes, 1);
+    }
+
+    direct_children(path)
+        .iter()
+        .map(|child| tree_size(child, false))
+        .fold((0, 0), |(bytes, files), (more_bytes, more_files)| {
+            (bytes + more_bytes, files + more_files)
+        })
+}
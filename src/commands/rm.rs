@@ -1,56 +0,0 @@
-use std::fs;
-use std::path::Path;
-
-pub fn rm_handler(args: Vec<String>) {
-    if args.is_empty() {
-        eprintln!("Usage: rm [-r] <file_or_directory>");
-        return;
-    }
-
-    let mut dir_flag = false;
-    let mut targets= Vec::new();
-
-    //check -r flage
-    for arg in args {
-
-        //check: refuse to remove the current (".") or parent ("..") directory
-        if arg.ends_with("/.") || arg.ends_with("/..") {
-            eprintln!("rm: refusing to remove '.' or '..' directory: skipping '{}'", arg);
-            continue;
-        }
-         match arg.as_str() {
-            "-r" => dir_flag = true,
-            "." | ".." => eprintln!("rm: refusing to remove '.' or '..'"),
-            _ => targets.push(arg),
-        }
-    }
-
-    if targets.is_empty() {
-        eprintln!("rm: missing operand");
-        return;
-    }
-
-    for target in targets {
-        let path = Path::new(&target);
-
-        if !path.exists() {
-            eprintln!("rm: cannot remove '{}': No such file or directory", target);
-            continue;
-        }
-
-        if path.is_dir() {
-            if dir_flag {
-                if let Err(e) = fs::remove_dir_all(path) {
-                    eprintln!("rm: failed to remove directory '{}': {}", target, e);
-                }
-            } else {
-                eprintln!("rm: cannot remove '{}': Is a directory", target);
-            }
-        }else{
-            // it s a fifo or another types ...  remove it
-            if let Err(e) = fs::remove_file(path) {
-                eprintln!("rm: failed to remove '{}': {}", target, e);
-            }
-        }
-    }
-}
@@ -0,0 +1,169 @@
+use std::{
+    ffi::{CStr, CString},
+    fs::Metadata,
+    io,
+    mem::MaybeUninit,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::{ffi::OsStrExt, fs::MetadataExt},
+    },
+    path::Path,
+};
+
+const OPEN_FLAGS: libc::c_int =
+    libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+
+// the parts of a stat that removing needs, from lstat or fstatat alike
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stat {
+    pub mode: u32,
+    pub dev: u64,
+    pub ino: u64,
+    pub size: u64,
+}
+
+impl Stat {
+    pub fn is_dir(&self) -> bool {
+        self.mode & libc::S_IFMT == libc::S_IFDIR
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode & libc::S_IFMT == libc::S_IFLNK
+    }
+
+    pub fn same_file(&self, other: &Stat) -> bool {
+        self.dev == other.dev && self.ino == other.ino
+    }
+
+    fn from_raw(stat: &libc::stat) -> Self {
+        Self {
+            mode: stat.st_mode,
+            dev: stat.st_dev,
+            ino: stat.st_ino,
+            size: stat.st_size as u64,
+        }
+    }
+}
+
+impl From<&Metadata> for Stat {
+    fn from(meta: &Metadata) -> Self {
+        Self {
+            mode: meta.mode(),
+            dev: meta.dev(),
+            ino: meta.ino(),
+            size: meta.size(),
+        }
+    }
+}
+
+// a directory opened without following symlinks; its entries are looked at and removed
+// relative to it, so none of them can be swapped for a symlink to somewhere else between
+// the two, and no path above it is walked again
+#[derive(Debug)]
+pub struct Dir {
+    fd: OwnedFd,
+}
+
+impl Dir {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        Self::open_at(libc::AT_FDCWD, &path)
+    }
+
+    pub fn open_child(&self, name: &CStr) -> io::Result<Self> {
+        Self::open_at(self.fd.as_raw_fd(), name)
+    }
+
+    fn open_at(dirfd: RawFd, name: &CStr) -> io::Result<Self> {
+        let fd = unsafe { libc::openat(dirfd, name.as_ptr(), OPEN_FLAGS) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    pub fn stat(&self) -> io::Result<Stat> {
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+        if unsafe { libc::fstat(self.fd.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let stat = unsafe { stat.assume_init() };
+        Ok(Stat::from_raw(&stat))
+    }
+
+    pub fn stat_child(&self, name: &CStr) -> io::Result<Stat> {
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+        let result = unsafe {
+            libc::fstatat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                stat.as_mut_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let stat = unsafe { stat.assume_init() };
+        Ok(Stat::from_raw(&stat))
+    }
+
+    // every name but `.` and `..`, read in full before anything is removed
+    pub fn entries(&self) -> io::Result<Vec<CString>> {
+        // closedir closes the descriptor it was given, so it gets a copy
+        let fd = unsafe { libc::fcntl(self.fd.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let dir = unsafe { libc::fdopendir(fd) };
+        if dir.is_null() {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+        // the copy shares the offset, which an earlier read may have moved
+        unsafe { libc::rewinddir(dir) };
+
+        let mut names = Vec::new();
+        let result = loop {
+            unsafe { *libc::__errno_location() = 0 };
+            let entry = unsafe { libc::readdir(dir) };
+            if entry.is_null() {
+                break match io::Error::last_os_error() {
+                    err if err.raw_os_error() == Some(0) => Ok(names),
+                    err => Err(err),
+                };
+            }
+
+            let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+            if name != c"." && name != c".." {
+                names.push(name.to_owned());
+            }
+        };
+        unsafe { libc::closedir(dir) };
+        result
+    }
+
+    // whether the user may write to an entry, which decides if rm asks first
+    pub fn child_writable(&self, name: &CStr) -> bool {
+        let result = unsafe {
+            libc::faccessat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                libc::W_OK,
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        result == 0
+    }
+
+    pub fn remove_child(&self, name: &CStr, is_dir: bool) -> io::Result<()> {
+        let flags = if is_dir { libc::AT_REMOVEDIR } else { 0 };
+        if unsafe { libc::unlinkat(self.fd.as_raw_fd(), name.as_ptr(), flags) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,379 @@
+mod dirfd;
+
+use super::overwrite::is_read_only;
+use crate::utils::{confirm, error_message};
+use dirfd::{Dir, Stat};
+use std::{
+    ffi::{CStr, OsStr},
+    fs::{self, Metadata},
+    io::{self, IsTerminal},
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::Path,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    // only write-protected files are asked about, and only on a terminal
+    #[default]
+    Default,
+    // -f
+    Never,
+    // -I: once, before removing recursively or more than three files
+    Once,
+    // -i
+    Always,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum PreserveRoot {
+    No,
+    // refuse to remove `/` recursively
+    #[default]
+    Yes,
+    // also refuse operands on another filesystem than their parent
+    All,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Flags {
+    recursive: bool,
+    // -d: empty directories are removed like files
+    dir: bool,
+    // -f: missing operands are not an error
+    force: bool,
+    prompt: Prompt,
+    verbose: bool,
+    one_file_system: bool,
+    preserve_root: PreserveRoot,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Rm {
+    flags: Flags,
+    pub operands: Vec<String>,
+}
+
+pub fn rm_handler(args: Vec<String>) {
+    match Rm::new(args) {
+        Ok(rm) => rm.execute(),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+impl Rm {
+    pub fn new(args: Vec<String>) -> Result<Self, String> {
+        let mut rm = Self::default();
+        let mut only_operands = false;
+
+        for arg in args {
+            if only_operands || arg == "-" || !arg.starts_with('-') {
+                rm.operands.push(arg);
+            } else if arg == "--" {
+                only_operands = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                rm.parse_long_flag(long)?;
+            } else {
+                rm.parse_flags(&arg)?;
+            }
+        }
+        Ok(rm)
+    }
+
+    fn parse_flags(&mut self, arg: &str) -> Result<(), String> {
+        for ch in arg.chars().skip(1) {
+            match ch {
+                'r' | 'R' => self.flags.recursive = true,
+                'd' => self.flags.dir = true,
+                'v' => self.flags.verbose = true,
+                'f' => self.set_prompt(Prompt::Never),
+                'i' => self.set_prompt(Prompt::Always),
+                'I' => self.set_prompt(Prompt::Once),
+                _ => return Err(format!("rm: invalid option -- '{}'", ch)),
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_long_flag(&mut self, arg: &str) -> Result<(), String> {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg, None),
+        };
+
+        match (name, value) {
+            ("recursive", None) => self.flags.recursive = true,
+            ("dir", None) => self.flags.dir = true,
+            ("verbose", None) => self.flags.verbose = true,
+            ("force", None) => self.set_prompt(Prompt::Never),
+            ("interactive", None | Some("always" | "yes")) => self.set_prompt(Prompt::Always),
+            ("interactive", Some("once")) => self.set_prompt(Prompt::Once),
+            ("interactive", Some("never" | "no" | "none")) => self.flags.prompt = Prompt::Never,
+            ("interactive", Some(when)) => {
+                return Err(format!(
+                    "rm: invalid argument '{}' for '--interactive'\nValid arguments are:\n  - 'never', 'no', 'none'\n  - 'once'\n  - 'always', 'yes'",
+                    when
+                ));
+            }
+            ("one-file-system", None) => self.flags.one_file_system = true,
+            ("preserve-root", None) => self.flags.preserve_root = PreserveRoot::Yes,
+            ("preserve-root", Some("all")) => self.flags.preserve_root = PreserveRoot::All,
+            ("preserve-root", Some(value)) => {
+                return Err(format!(
+                    "rm: unrecognized --preserve-root argument: '{}'",
+                    value
+                ));
+            }
+            ("no-preserve-root", None) => self.flags.preserve_root = PreserveRoot::No,
+            _ => return Err(format!("rm: unrecognized option '--{}'", arg)),
+        }
+        Ok(())
+    }
+
+    // the last of -f, -i and -I wins, and only -f forgives missing files
+    fn set_prompt(&mut self, prompt: Prompt) {
+        self.flags.prompt = prompt;
+        self.flags.force = prompt == Prompt::Never;
+    }
+
+    fn execute(&self) {
+        if self.operands.is_empty() {
+            if !self.flags.force {
+                eprintln!("rm: missing operand");
+            }
+            return;
+        }
+
+        if self.flags.prompt == Prompt::Once && (self.flags.recursive || self.operands.len() > 3) {
+            let count = self.operands.len();
+            let arguments = if count == 1 { "argument" } else { "arguments" };
+            let how = if self.flags.recursive {
+                " recursively"
+            } else {
+                ""
+            };
+            if !confirm(&format!("rm: remove {} {}{}? ", count, arguments, how)) {
+                return;
+            }
+        }
+
+        for operand in &self.operands {
+            if let Err(e) = self.remove_operand(operand) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    fn remove_operand(&self, operand: &str) -> Result<(), String> {
+        let path = Path::new(operand);
+        let last = operand.trim_end_matches('/').rsplit('/').next();
+        if matches!(last, Some("." | "..")) {
+            return Err(format!(
+                "rm: refusing to remove '.' or '..' directory: skipping '{}'",
+                operand
+            ));
+        }
+
+        let meta = match fs::symlink_metadata(path) {
+            Ok(meta) => meta,
+            Err(e) if self.flags.force && e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(cannot_remove(path, &e)),
+        };
+
+        if meta.is_dir() && self.flags.recursive && self.flags.preserve_root != PreserveRoot::No {
+            if is_root(path) {
+                return Err(format!(
+                    "rm: it is dangerous to operate recursively on '{}'\nrm: use --no-preserve-root to override this failsafe",
+                    operand
+                ));
+            }
+            if self.flags.preserve_root == PreserveRoot::All && on_other_device(path, &meta) {
+                return Err(format!(
+                    "rm: skipping '{}', since it's on a different device\nrm: and --preserve-root=all is in effect",
+                    operand
+                ));
+            }
+        }
+
+        self.remove(path, &Stat::from(&meta), is_read_only(path, &meta));
+        Ok(())
+    }
+
+    // reports its own errors, and tells whether `path` is gone so a parent is only removed
+    // once everything in it is
+    fn remove(&self, path: &Path, stat: &Stat, protected: bool) -> bool {
+        if !stat.is_dir() {
+            if !self.ask("remove", path, stat, protected) {
+                return false;
+            }
+            return self.finish(fs::remove_file(path), path, "removed");
+        }
+
+        if !self.flags.recursive {
+            if !self.flags.dir {
+                eprintln!("rm: cannot remove '{}': Is a directory", path.display());
+                return false;
+            }
+            if !self.ask("remove", path, stat, protected) {
+                return false;
+            }
+            return self.finish(fs::remove_dir(path), path, "removed directory");
+        }
+
+        let dir = match self.open_checked(Dir::open(path), path, stat) {
+            Some(dir) => dir,
+            None => return false,
+        };
+        if !self.empty(&dir, path, stat, protected, stat.dev) {
+            return false;
+        }
+        drop(dir);
+        self.finish(fs::remove_dir(path), path, "removed directory")
+    }
+
+    // an entry found while emptying `parent`, reached only through it
+    fn remove_child(&self, parent: &Dir, name: &CStr, path: &Path, root_dev: u64) -> bool {
+        let stat = match parent.stat_child(name) {
+            Ok(stat) => stat,
+            Err(e) => {
+                eprintln!("{}", cannot_remove(path, &e));
+                return false;
+            }
+        };
+        let protected =
+            io::stdin().is_terminal() && !stat.is_symlink() && !parent.child_writable(name);
+
+        if !stat.is_dir() {
+            if !self.ask("remove", path, &stat, protected) {
+                return false;
+            }
+            return self.finish(parent.remove_child(name, false), path, "removed");
+        }
+
+        if self.flags.one_file_system && stat.dev != root_dev {
+            eprintln!(
+                "rm: skipping '{}', since it's on a different device",
+                path.display()
+            );
+            return false;
+        }
+
+        let dir = match self.open_checked(parent.open_child(name), path, &stat) {
+            Some(dir) => dir,
+            None => return false,
+        };
+        if !self.empty(&dir, path, &stat, protected, root_dev) {
+            return false;
+        }
+        drop(dir);
+        self.finish(parent.remove_child(name, true), path, "removed directory")
+    }
+
+    // what was opened has to be the directory that was looked at, not something put in its
+    // place since; opening never follows a symlink
+    fn open_checked(&self, opened: io::Result<Dir>, path: &Path, stat: &Stat) -> Option<Dir> {
+        let checked = opened.and_then(|dir| Ok((dir.stat()?, dir)));
+        match checked {
+            Ok((opened, dir)) if opened.same_file(stat) => Some(dir),
+            Ok(_) => {
+                eprintln!(
+                    "rm: skipping '{}', since it changed while being removed",
+                    path.display()
+                );
+                None
+            }
+            Err(e) => {
+                eprintln!("{}", cannot_remove(path, &e));
+                None
+            }
+        }
+    }
+
+    // removes everything in a directory, carrying on past entries that fail, and tells
+    // whether the directory itself may go now
+    fn empty(&self, dir: &Dir, path: &Path, stat: &Stat, protected: bool, root_dev: u64) -> bool {
+        let names = match dir.entries() {
+            Ok(names) => names,
+            Err(e) => {
+                eprintln!("{}", cannot_remove(path, &e));
+                return false;
+            }
+        };
+        if !names.is_empty() && !self.ask("descend into", path, stat, protected) {
+            return false;
+        }
+
+        let mut emptied = true;
+        for name in names {
+            let child = path.join(OsStr::from_bytes(name.to_bytes()));
+            emptied &= self.remove_child(dir, &name, &child, root_dev);
+        }
+        emptied && self.ask("remove", path, stat, protected)
+    }
+
+    // -i asks about everything, and without -f a write-protected file is asked about too
+    fn ask(&self, action: &str, path: &Path, stat: &Stat, protected: bool) -> bool {
+        match self.flags.prompt {
+            Prompt::Always => {}
+            Prompt::Default if protected => {}
+            _ => return true,
+        }
+        let protection = if protected { "write-protected " } else { "" };
+        confirm(&format!(
+            "rm: {} {}{} '{}'? ",
+            action,
+            protection,
+            file_kind(stat),
+            path.display()
+        ))
+    }
+
+    fn finish(&self, result: io::Result<()>, path: &Path, done: &str) -> bool {
+        match result {
+            Ok(()) => {
+                if self.flags.verbose {
+                    println!("{} '{}'", done, path.display());
+                }
+                true
+            }
+            Err(e) => {
+                eprintln!("{}", cannot_remove(path, &e));
+                false
+            }
+        }
+    }
+}
+
+fn cannot_remove(path: &Path, err: &io::Error) -> String {
+    format!(
+        "rm: cannot remove '{}': {}",
+        path.display(),
+        error_message(err)
+    )
+}
+
+// what prompts call a file, as GNU rm does
+fn file_kind(stat: &Stat) -> &'static str {
+    match stat.mode & libc::S_IFMT {
+        libc::S_IFDIR => "directory",
+        libc::S_IFLNK => "symbolic link",
+        libc::S_IFREG if stat.size == 0 => "regular empty file",
+        libc::S_IFREG => "regular file",
+        libc::S_IFIFO => "fifo",
+        libc::S_IFSOCK => "socket",
+        libc::S_IFBLK => "block special file",
+        _ => "character special file",
+    }
+}
+
+// compared by device and inode, so `//` or `/tmp/..` count as well
+fn is_root(path: &Path) -> bool {
+    match (fs::metadata(path), fs::metadata("/")) {
+        (Ok(meta), Ok(root)) => meta.dev() == root.dev() && meta.ino() == root.ino(),
+        _ => false,
+    }
+}
+
+fn on_other_device(path: &Path, meta: &Metadata) -> bool {
+    let parent = path.join("..");
+    fs::metadata(parent).is_ok_and(|parent| parent.dev() != meta.dev())
+}
@@ -1,5 +1,6 @@
 mod commands;
 mod shell;
+mod syntax;
 mod utils;
 
 use std::{env, io::{self, IsTerminal}, path::PathBuf};
@@ -32,15 +33,14 @@ fn main() -> Result<(), String> {
             exit_handler();
         }
 
-        shell.set_args(vec![]);
-        shell.set_arg("".to_string());
-        shell.set_quotes_type('"');
+        let program = match shell.parse_input(input.as_str()) {
+            Ok(program) => program,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
 
-        if let Err(e) = shell.parse_input(input.as_str()) {
-            eprintln!("{}", e);
-            continue;
-        }
-
-        shell.run();
+        shell.run(program);
     }
 }
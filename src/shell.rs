@@ -1,15 +1,14 @@
 use std::path::PathBuf;
 
-use crate::{commands::*, utils::read_line};
+use crate::{
+    commands::*,
+    syntax::{self, Command, ListItem, ParseError, Pending, Program},
+    utils::read_line,
+};
 
 #[derive(Default)]
 pub struct Shell {
-    arg: String,
-    pub args: Vec<String>,
     pub home: String,
-    is_quotes: bool,
-    quotes_type: char,
-    is_backslash: bool,
     prev_path: PathBuf,
     pub current_path: PathBuf,
 }
@@ -21,144 +20,96 @@ impl Shell {
         }
     }
 
-    pub fn add_arg_with_quotes(&mut self, arg: String) {
-        if !arg.is_empty() {
-            self.args.push(arg);
-            self.arg.clear();
-        }
-    }
-
-    pub fn add_arg(&mut self) {
-        if !self.arg.is_empty() {
-            let mut arg = self.arg.clone().trim().to_string();
-            if arg == "~" || (arg.starts_with("~") && arg.chars().nth(1).unwrap_or(' ') == '/') {
-                arg = if arg.len() > 1 {
-                    self.home.clone() + &arg[1..arg.len()]
-                } else {
-                    self.home.clone()
-                }
-            }
-            self.args.push(arg);
-            self.arg.clear();
-        }
-    }
-
-    pub fn set_args(&mut self, value: Vec<String>) {
-        self.args = value;
-    }
-
-    pub fn set_arg(&mut self, value: String) {
-        self.arg = value
-    }
-
     pub fn set_current_path(&mut self, value: PathBuf) {
         if !value.as_os_str().is_empty() {
             self.current_path = value
         }
     }
 
-    pub fn set_quotes_type(&mut self, value: char) {
-        self.quotes_type = value
-    }
-
     pub fn set_home(&mut self, value: String) {
         self.home = value
     }
 
-    pub fn parse_input(&mut self, input: &str) -> Result<(), String> {
-        for (i, ch) in input.chars().enumerate() {
-            if self.is_backslash {
-                if (ch != '$' && ch != '`' && ch != '"' && ch != '\\') && self.is_quotes {
-                    self.arg.push('\\');
-                }
-                self.arg.push(ch);
-                self.is_backslash = false;
-                continue;
-            }
+    pub fn parse_input(&self, input: &str) -> Result<Program, String> {
+        let mut input = input.to_string();
 
-            if ch == '\\' && self.quotes_type != '\'' {
-                self.is_backslash = true;
-                continue;
-            }
-
-            if (ch == '"' || ch == '\'') && !self.is_quotes {
-                self.quotes_type = ch;
-                self.is_quotes = true;
-                continue;
-            }
+        loop {
+            let pending = match syntax::parse(&input) {
+                Ok(program) => return Ok(program),
+                Err(ParseError::Syntax(e)) => return Err(e),
+                Err(ParseError::Incomplete(pending)) => pending,
+            };
 
-            if ch == self.quotes_type {
-                self.is_quotes = false;
-                self.quotes_type = '"';
-                if input.chars().nth(i + 1).unwrap_or(' ') == ' ' {
-                    self.add_arg_with_quotes(self.arg.clone());
-                }
-                continue;
+            let (line, n_bytes) = read_line(pending.prompt(), &self.home);
+            if n_bytes == 0 {
+                return Err(match pending {
+                    Pending::SingleQuote | Pending::DoubleQuote => format!(
+                        "\nunexpected EOF while looking for matching `{}'\nsyntax error: unexpected end of file",
+                        if pending == Pending::SingleQuote {
+                            '\''
+                        } else {
+                            '"'
+                        }
+                    ),
+                    _ => "\nsyntax error: unexpected end of file".to_string(),
+                });
             }
 
-            if ch == ' ' && !self.is_quotes && !self.arg.is_empty() {
-                self.add_arg();
-                continue;
-            }
+            input.push('\n');
+            input.push_str(&line);
+        }
+    }
 
-            if ch != ' ' || (ch == ' ' && self.is_quotes) {
-                self.arg.push(ch);
-            }
+    pub fn run(&mut self, program: Program) {
+        for item in program.items {
+            self.run_item(item);
         }
+    }
 
-        if self.is_backslash {
-            self.is_backslash = false;
-            let (input, nb_bytes) = read_line(">", &self.home);
-            if nb_bytes == 0 {
-                println!();
-            }
-            if let Err(e) = self.parse_input(input.as_str()) {
-                return Err(e);
-            }
+    fn run_item(&mut self, item: ListItem) {
+        if item.background {
+            eprintln!("0-shell: background jobs are not supported");
+            return;
+        }
+        if !item.and_or.rest.is_empty() {
+            eprintln!("0-shell: `&&' and `||' lists are not supported");
+            return;
         }
 
-        if !self.arg.is_empty() && !self.is_quotes {
-            self.add_arg();
+        let pipeline = item.and_or.first;
+        if pipeline.negated || pipeline.commands.len() > 1 {
+            eprintln!("0-shell: pipelines are not supported");
+            return;
         }
 
-        if self.is_quotes {
-            let quote_text = if self.quotes_type == '\'' {
-                "quote>"
-            } else {
-                "dquote>"
-            };
-            let (input, n_bytes) = read_line(quote_text, &self.home);
-            if n_bytes == 0 {
-                self.is_quotes = false;
-                return Err(format!(
-                    "\nunexpected EOF while looking for matching `{}'\nsyntax error: unexpected end of file",
-                    self.quotes_type
-                ));
-            }
-            self.arg.push('\n');
-            if let Err(e) = self.parse_input(input.as_str()) {
-                return Err(e); 
+        let command = match pipeline.commands.into_iter().next() {
+            Some(Command::Simple(command)) => command,
+            Some(Command::Subshell(..)) => {
+                eprintln!("0-shell: subshells are not supported");
+                return;
             }
+            None => return,
+        };
+        if !command.redirections.is_empty() {
+            eprintln!("0-shell: redirections are not supported");
+            return;
         }
 
-        Ok(())
+        let args = command
+            .words
+            .iter()
+            .map(|word| word.expand(&self.home))
+            .collect();
+        self.run_command(args);
     }
 
-    pub fn run(&mut self) {
-        let empty_cmd = &"".to_string();
-        let cmd: &str = &self.args.iter().next().unwrap_or(empty_cmd);
-
-        if cmd.is_empty() {
+    fn run_command(&mut self, mut args: Vec<String>) {
+        if args.is_empty() {
             return;
         }
+        let cmd = args.remove(0);
 
-        let args: Vec<String> = if self.args.len() > 1 {
-            self.args[1..self.args.len()].to_vec()
-        } else {
-            vec![]
-        };
-
-        match cmd {
+        match cmd.as_str() {
             "cat" => cat_handler(args),
             "cd" => {
                 let (prev_path, current_path) = cd_handler(
@@ -0,0 +1,373 @@
+use std::{iter::Peekable, str::Chars};
+
+use super::ParseError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(Word),
+    IoNumber(u32),
+    Operator(Operator),
+    Comment(String),
+    Newline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Semi,
+    Amp,
+    Pipe,
+    AndIf,
+    OrIf,
+    Less,
+    Great,
+    DLess,
+    DGreat,
+    LessAnd,
+    GreatAnd,
+    LessGreat,
+    Clobber,
+    LParen,
+    RParen,
+}
+
+impl Operator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Semi => ";",
+            Operator::Amp => "&",
+            Operator::Pipe => "|",
+            Operator::AndIf => "&&",
+            Operator::OrIf => "||",
+            Operator::Less => "<",
+            Operator::Great => ">",
+            Operator::DLess => "<<",
+            Operator::DGreat => ">>",
+            Operator::LessAnd => "<&",
+            Operator::GreatAnd => ">&",
+            Operator::LessGreat => "<>",
+            Operator::Clobber => ">|",
+            Operator::LParen => "(",
+            Operator::RParen => ")",
+        }
+    }
+}
+
+// a piece of a word, remembering how it was quoted in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    Literal(String),
+    Escaped(char),
+    SingleQuoted(String),
+    // only Literal and Escaped parts, so `"\$x"` stays apart from `"$x"`; a backslash
+    // before anything else is part of the literal text
+    DoubleQuoted(Vec<WordPart>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl Word {
+    // the word as typed, with quotes removed and no expansion applied
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        push_text(&self.parts, &mut text);
+        text
+    }
+
+    // quote removal plus tilde expansion of an unquoted leading `~` or `~/`
+    pub fn expand(&self, home: &str) -> String {
+        let text = self.text();
+        let tilde = match self.parts.first() {
+            Some(WordPart::Literal(first)) => {
+                (first == "~" && self.parts.len() == 1) || first.starts_with("~/")
+            }
+            _ => false,
+        };
+
+        if tilde {
+            format!("{}{}", home, &text[1..])
+        } else {
+            text
+        }
+    }
+}
+
+fn push_text(parts: &[WordPart], text: &mut String) {
+    for part in parts {
+        match part {
+            WordPart::Literal(s) | WordPart::SingleQuoted(s) => text.push_str(s),
+            WordPart::Escaped(ch) => text.push(*ch),
+            WordPart::DoubleQuoted(inner) => push_text(inner, text),
+        }
+    }
+}
+
+fn push_literal(parts: &mut Vec<WordPart>, ch: char) {
+    if let Some(WordPart::Literal(s)) = parts.last_mut() {
+        s.push(ch);
+    } else {
+        parts.push(WordPart::Literal(ch.to_string()));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pending {
+    SingleQuote,
+    DoubleQuote,
+    Backslash,
+    Command,
+}
+
+impl Pending {
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            Pending::SingleQuote => "quote>",
+            Pending::DoubleQuote => "dquote>",
+            Pending::Backslash | Pending::Command => ">",
+        }
+    }
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    Lexer::new(input).run()
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    tokens: Vec<Token>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.chars().peekable(),
+            tokens: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Result<Vec<Token>, ParseError> {
+        while let Some(&ch) = self.chars.peek() {
+            match ch {
+                '\n' => {
+                    self.chars.next();
+                    self.tokens.push(Token::Newline);
+                }
+                ' ' | '\t' => {
+                    self.chars.next();
+                }
+                '#' => self.read_comment(),
+                _ if is_operator_start(ch) => self.read_operator(),
+                _ => self.read_word()?,
+            }
+        }
+        Ok(self.tokens)
+    }
+
+    fn read_comment(&mut self) {
+        self.chars.next();
+        let mut comment = String::new();
+        while let Some(&ch) = self.chars.peek() {
+            if ch == '\n' {
+                break;
+            }
+            comment.push(ch);
+            self.chars.next();
+        }
+        self.tokens.push(Token::Comment(comment));
+    }
+
+    fn read_operator(&mut self) {
+        let first = self.chars.next().unwrap_or_default();
+        let next = self.chars.peek().copied();
+        let (op, two_chars) = match (first, next) {
+            ('&', Some('&')) => (Operator::AndIf, true),
+            ('|', Some('|')) => (Operator::OrIf, true),
+            ('<', Some('<')) => (Operator::DLess, true),
+            ('<', Some('&')) => (Operator::LessAnd, true),
+            ('<', Some('>')) => (Operator::LessGreat, true),
+            ('>', Some('>')) => (Operator::DGreat, true),
+            ('>', Some('&')) => (Operator::GreatAnd, true),
+            ('>', Some('|')) => (Operator::Clobber, true),
+            (';', _) => (Operator::Semi, false),
+            ('&', _) => (Operator::Amp, false),
+            ('|', _) => (Operator::Pipe, false),
+            ('<', _) => (Operator::Less, false),
+            ('>', _) => (Operator::Great, false),
+            ('(', _) => (Operator::LParen, false),
+            _ => (Operator::RParen, false),
+        };
+        if two_chars {
+            self.chars.next();
+        }
+        self.tokens.push(Token::Operator(op));
+    }
+
+    fn read_word(&mut self) -> Result<(), ParseError> {
+        let mut word = Word::default();
+
+        while let Some(&ch) = self.chars.peek() {
+            if ch == ' ' || ch == '\t' || ch == '\n' || is_operator_start(ch) {
+                break;
+            }
+            self.chars.next();
+
+            match ch {
+                '\\' => match self.chars.next() {
+                    Some('\n') => {}
+                    Some(escaped) => word.parts.push(WordPart::Escaped(escaped)),
+                    None => return Err(ParseError::Incomplete(Pending::Backslash)),
+                },
+                '\'' => {
+                    let quoted = self.read_single_quoted()?;
+                    word.parts.push(WordPart::SingleQuoted(quoted));
+                }
+                '"' => {
+                    let quoted = self.read_double_quoted()?;
+                    word.parts.push(WordPart::DoubleQuoted(quoted));
+                }
+                _ => push_literal(&mut word.parts, ch),
+            }
+        }
+
+        if word.parts.is_empty() {
+            return Ok(());
+        }
+
+        if let [WordPart::Literal(digits)] = word.parts.as_slice() {
+            let before_redirect = matches!(self.chars.peek(), Some('<') | Some('>'));
+            if before_redirect
+                && digits.bytes().all(|b| b.is_ascii_digit())
+                && let Ok(fd) = digits.parse()
+            {
+                self.tokens.push(Token::IoNumber(fd));
+                return Ok(());
+            }
+        }
+
+        self.tokens.push(Token::Word(word));
+        Ok(())
+    }
+
+    fn read_single_quoted(&mut self) -> Result<String, ParseError> {
+        let mut quoted = String::new();
+        loop {
+            match self.chars.next() {
+                Some('\'') => return Ok(quoted),
+                Some(ch) => quoted.push(ch),
+                None => return Err(ParseError::Incomplete(Pending::SingleQuote)),
+            }
+        }
+    }
+
+    fn read_double_quoted(&mut self) -> Result<Vec<WordPart>, ParseError> {
+        let mut quoted = Vec::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(quoted),
+                Some('\\') => match self.chars.next() {
+                    Some('\n') => {}
+                    Some(ch @ ('$' | '`' | '"' | '\\')) => quoted.push(WordPart::Escaped(ch)),
+                    Some(ch) => {
+                        push_literal(&mut quoted, '\\');
+                        push_literal(&mut quoted, ch);
+                    }
+                    None => return Err(ParseError::Incomplete(Pending::DoubleQuote)),
+                },
+                Some(ch) => push_literal(&mut quoted, ch),
+                None => return Err(ParseError::Incomplete(Pending::DoubleQuote)),
+            }
+        }
+    }
+}
+
+fn is_operator_start(ch: char) -> bool {
+    matches!(ch, ';' | '&' | '|' | '<' | '>' | '(' | ')')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(input: &str) -> Vec<Word> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Word(word) => Some(word),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quotes_join_one_word() {
+        let words = words(r#"a"b"c"#);
+        assert_eq!(words.len(), 1);
+        assert_eq!(
+            words[0].parts,
+            vec![
+                WordPart::Literal("a".to_string()),
+                WordPart::DoubleQuoted(vec![WordPart::Literal("b".to_string())]),
+                WordPart::Literal("c".to_string()),
+            ]
+        );
+        assert_eq!(words[0].text(), "abc");
+    }
+
+    #[test]
+    fn an_escaped_dollar_stays_escaped_in_double_quotes() {
+        let escaped = words(r#""\$HOME""#);
+        let live = words(r#""$HOME""#);
+        assert_ne!(escaped, live);
+        assert_eq!(
+            escaped[0].parts,
+            vec![WordPart::DoubleQuoted(vec![
+                WordPart::Escaped('$'),
+                WordPart::Literal("HOME".to_string()),
+            ])]
+        );
+        assert_eq!(
+            live[0].parts,
+            vec![WordPart::DoubleQuoted(vec![WordPart::Literal(
+                "$HOME".to_string()
+            )])]
+        );
+        assert_eq!(escaped[0].text(), live[0].text());
+        assert_eq!(words(r#""a\b""#)[0].text(), r"a\b");
+    }
+
+    #[test]
+    fn empty_quotes_are_a_word() {
+        let words = words(r#""""#);
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].text(), "");
+    }
+
+    #[test]
+    fn only_an_unquoted_tilde_expands() {
+        assert_eq!(words("~/x")[0].expand("/home/me"), "/home/me/x");
+        assert_eq!(words(r#""~"/x"#)[0].expand("/home/me"), "~/x");
+    }
+
+    #[test]
+    fn digits_before_a_redirection_are_an_io_number() {
+        let tokens = tokenize("2>f").unwrap();
+        assert_eq!(tokens[0], Token::IoNumber(2));
+        assert_eq!(tokens[1], Token::Operator(Operator::Great));
+        assert!(matches!(&tokens[2], Token::Word(word) if word.text() == "f"));
+    }
+
+    #[test]
+    fn a_comment_starts_only_a_word() {
+        let tokens = tokenize("a#b").unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(matches!(&tokens[0], Token::Word(word) if word.text() == "a#b"));
+
+        let tokens = tokenize("a #b").unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(matches!(&tokens[0], Token::Word(word) if word.text() == "a"));
+        assert_eq!(tokens[1], Token::Comment("b".to_string()));
+    }
+}
@@ -0,0 +1,18 @@
+pub mod ast;
+pub mod lexer;
+pub mod parser;
+
+pub use ast::*;
+pub use lexer::*;
+pub use parser::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    // the input stops in the middle of a quote, escape or command and needs another line
+    Incomplete(Pending),
+    Syntax(String),
+}
+
+pub fn parse(input: &str) -> Result<Program, ParseError> {
+    parse_tokens(tokenize(input)?)
+}
@@ -0,0 +1,269 @@
+use std::{iter::Peekable, vec::IntoIter};
+
+use super::{
+    ParseError,
+    ast::*,
+    lexer::{Operator, Pending, Token, Word, WordPart},
+};
+
+pub fn parse_tokens(tokens: Vec<Token>) -> Result<Program, ParseError> {
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Comment(_)))
+        .collect();
+
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+    };
+    let program = parser.parse_program(false)?;
+
+    match parser.tokens.next() {
+        None => Ok(program),
+        Some(token) => Err(unexpected(&token)),
+    }
+}
+
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+}
+
+impl Parser {
+    fn parse_program(&mut self, in_subshell: bool) -> Result<Program, ParseError> {
+        let mut program = Program::default();
+
+        loop {
+            self.skip_newlines();
+            match self.tokens.peek() {
+                None => break,
+                Some(Token::Operator(Operator::RParen)) if in_subshell => break,
+                _ => {}
+            }
+
+            let and_or = self.parse_and_or()?;
+            let background = match self.tokens.peek() {
+                Some(Token::Operator(Operator::Amp)) => {
+                    self.tokens.next();
+                    true
+                }
+                Some(Token::Operator(Operator::Semi)) | Some(Token::Newline) => {
+                    self.tokens.next();
+                    false
+                }
+                Some(Token::Operator(Operator::RParen)) if in_subshell => false,
+                None => false,
+                Some(token) => return Err(unexpected(token)),
+            };
+
+            program.items.push(ListItem { and_or, background });
+        }
+
+        Ok(program)
+    }
+
+    fn parse_and_or(&mut self) -> Result<AndOr, ParseError> {
+        let first = self.parse_pipeline()?;
+        let mut rest = Vec::new();
+
+        loop {
+            let connector = match self.tokens.peek() {
+                Some(Token::Operator(Operator::AndIf)) => Connector::And,
+                Some(Token::Operator(Operator::OrIf)) => Connector::Or,
+                _ => break,
+            };
+            self.tokens.next();
+            self.skip_newlines();
+            rest.push((connector, self.parse_pipeline()?));
+        }
+
+        Ok(AndOr { first, rest })
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let negated = matches!(self.tokens.peek(), Some(Token::Word(word)) if is_bang(word));
+        if negated {
+            self.tokens.next();
+        }
+
+        let mut commands = vec![self.parse_command()?];
+        while let Some(Token::Operator(Operator::Pipe)) = self.tokens.peek() {
+            self.tokens.next();
+            self.skip_newlines();
+            commands.push(self.parse_command()?);
+        }
+
+        Ok(Pipeline { negated, commands })
+    }
+
+    fn parse_command(&mut self) -> Result<Command, ParseError> {
+        if let Some(Token::Operator(Operator::LParen)) = self.tokens.peek() {
+            self.tokens.next();
+            let program = self.parse_program(true)?;
+            match self.tokens.next() {
+                Some(Token::Operator(Operator::RParen)) => {}
+                Some(token) => return Err(unexpected(&token)),
+                None => return Err(ParseError::Incomplete(Pending::Command)),
+            }
+            if program.items.is_empty() {
+                return Err(ParseError::Syntax(
+                    "syntax error near unexpected token `)'".to_string(),
+                ));
+            }
+
+            let mut redirections = Vec::new();
+            while let Some(redirection) = self.parse_redirection()? {
+                redirections.push(redirection);
+            }
+            return Ok(Command::Subshell(program, redirections));
+        }
+
+        let mut command = SimpleCommand::default();
+        loop {
+            if let Some(redirection) = self.parse_redirection()? {
+                command.redirections.push(redirection);
+                continue;
+            }
+
+            match self.tokens.peek() {
+                Some(Token::Word(_)) => {
+                    if let Some(Token::Word(word)) = self.tokens.next() {
+                        command.words.push(word);
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        if command.words.is_empty() && command.redirections.is_empty() {
+            return match self.tokens.peek() {
+                None => Err(ParseError::Incomplete(Pending::Command)),
+                Some(token) => Err(unexpected(token)),
+            };
+        }
+
+        Ok(Command::Simple(command))
+    }
+
+    fn parse_redirection(&mut self) -> Result<Option<Redirection>, ParseError> {
+        let fd = match self.tokens.peek() {
+            Some(Token::IoNumber(fd)) => Some(*fd),
+            Some(Token::Operator(op)) if redirection_kind(*op).is_some() => None,
+            _ => return Ok(None),
+        };
+        if fd.is_some() {
+            self.tokens.next();
+        }
+
+        let kind = match self.tokens.next() {
+            Some(Token::Operator(op)) => match redirection_kind(op) {
+                Some(kind) => kind,
+                None => return Err(unexpected(&Token::Operator(op))),
+            },
+            Some(token) => return Err(unexpected(&token)),
+            None => return Err(unexpected_eof()),
+        };
+
+        match self.tokens.next() {
+            Some(Token::Word(target)) => Ok(Some(Redirection { fd, kind, target })),
+            Some(token) => Err(unexpected(&token)),
+            None => Err(unexpected_eof()),
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while let Some(Token::Newline) = self.tokens.peek() {
+            self.tokens.next();
+        }
+    }
+}
+
+fn redirection_kind(op: Operator) -> Option<RedirectionKind> {
+    match op {
+        Operator::Less => Some(RedirectionKind::Input),
+        Operator::Great => Some(RedirectionKind::Output),
+        Operator::DGreat => Some(RedirectionKind::Append),
+        Operator::DLess => Some(RedirectionKind::HereDoc),
+        Operator::LessAnd => Some(RedirectionKind::DupInput),
+        Operator::GreatAnd => Some(RedirectionKind::DupOutput),
+        Operator::LessGreat => Some(RedirectionKind::ReadWrite),
+        Operator::Clobber => Some(RedirectionKind::Clobber),
+        _ => None,
+    }
+}
+
+fn is_bang(word: &Word) -> bool {
+    matches!(word.parts.as_slice(), [WordPart::Literal(s)] if s == "!")
+}
+
+fn unexpected(token: &Token) -> ParseError {
+    let text = match token {
+        Token::Word(word) => word.text(),
+        Token::IoNumber(fd) => fd.to_string(),
+        Token::Operator(op) => op.as_str().to_string(),
+        Token::Comment(_) | Token::Newline => "newline".to_string(),
+    };
+    ParseError::Syntax(format!("syntax error near unexpected token `{}'", text))
+}
+
+fn unexpected_eof() -> ParseError {
+    ParseError::Syntax("syntax error near unexpected token `newline'".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Pending, parse, tokenize};
+    use super::*;
+
+    // characters the lexer and parser treat specially, plus a few that they don't
+    const ALPHABET: &[char] = &[
+        'a', 'b', '1', '2', ' ', '\t', '\n', '\\', '\'', '"', '#', '~', '/', '!', ';', '&', '|',
+        '<', '>', '(', ')', 'é',
+    ];
+
+    // xorshift, so every run goes through the same inputs
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn input(&mut self, alphabet: &[char]) -> String {
+            let len = self.next() % 24;
+            (0..len)
+                .map(|_| alphabet[(self.next() % alphabet.len() as u64) as usize])
+                .collect()
+        }
+    }
+
+    #[test]
+    fn a_trailing_pipe_needs_another_line() {
+        assert_eq!(parse("ls |"), Err(ParseError::Incomplete(Pending::Command)));
+    }
+
+    #[test]
+    fn parse_never_panics() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200_000 {
+            let input = rng.input(ALPHABET);
+            let _ = parse(&input);
+        }
+    }
+
+    #[test]
+    fn single_quoted_text_comes_back_unchanged() {
+        let alphabet: Vec<char> = ALPHABET.iter().copied().filter(|&ch| ch != '\'').collect();
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..10_000 {
+            let text = rng.input(&alphabet);
+            let tokens = tokenize(&format!("'{}'", text)).unwrap();
+            assert!(
+                matches!(tokens.as_slice(), [Token::Word(word)] if word.text() == text),
+                "{:?}",
+                text
+            );
+        }
+    }
+}
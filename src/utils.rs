@@ -35,4 +35,62 @@ pub fn direct_children(dir: &Path) -> Vec<PathBuf> {
         }
     }
     children
-}
\ No newline at end of file
+}
+
+// the message of an io error without the " (os error N)" suffix, as coreutils prints it
+pub fn error_message(err: &io::Error) -> String {
+    let message = err.to_string();
+    match err.raw_os_error() {
+        Some(code) => message.replace(&format!(" (os error {})", code), ""),
+        None => message,
+    }
+}
+
+// rounds up to one decimal below 10, like `ls -h` (base 1024) and `ls --si` (base 1000)
+pub fn human_size(bytes: u64, base: u64) -> String {
+    const UNITS: [char; 8] = ['K', 'M', 'G', 'T', 'P', 'E', 'Z', 'Y'];
+    if bytes < base {
+        return bytes.to_string();
+    }
+
+    let unit_name = |unit: usize| {
+        if base == 1000 && unit == 0 {
+            'k'
+        } else {
+            UNITS[unit]
+        }
+    };
+
+    let mut value = bytes as f64 / base as f64;
+    let mut unit = 0;
+    while value >= base as f64 && unit + 1 < UNITS.len() {
+        value /= base as f64;
+        unit += 1;
+    }
+
+    if value < 10.0 {
+        let rounded = (value * 10.0).ceil() / 10.0;
+        if rounded < 10.0 {
+            return format!("{:.1}{}", rounded, unit_name(unit));
+        }
+        return format!("10{}", unit_name(unit));
+    }
+
+    let rounded = value.ceil();
+    if rounded >= base as f64 && unit + 1 < UNITS.len() {
+        return format!("1.0{}", unit_name(unit + 1));
+    }
+    format!("{}{}", rounded, unit_name(unit))
+}
+
+// asks a yes/no question on stderr, and only an answer starting with `y` agrees
+pub fn confirm(prompt: &str) -> bool {
+    eprint!("{}", prompt);
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim_start().chars().next(), Some('y' | 'Y'))
+}